@@ -0,0 +1,230 @@
+use crate::incidents::{Incident, IncidentKind};
+use lazy_static::lazy_static;
+use serde::Serialize;
+use serde_json::{json, Value};
+use std::sync::Mutex;
+use tokio::sync::mpsc::{unbounded_channel, UnboundedReceiver, UnboundedSender};
+use tokio::time::{interval, Duration, Instant};
+use tracing::{info, warn};
+use url::Url;
+
+/// Discord rejects messages longer than this many characters.
+const DISCORD_MAX_CONTENT: usize = 2000;
+
+/// How many times a batch of alerts is delivered to a sink before it is
+/// dropped, so a sink which is down doesn't accumulate alerts forever.
+const MAX_DELIVERY_ATTEMPTS: u32 = 3;
+
+/// How long delivering a message to a sink may take.
+const DELIVERY_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Where alerts are delivered to.
+#[derive(Debug, Clone)]
+pub enum Sink {
+    /// Receives every alert as JSON in a POST request.
+    Webhook(Url),
+    /// A Slack incoming webhook.
+    Slack(Url),
+    /// A Discord channel webhook.
+    Discord(Url),
+}
+
+impl Sink {
+    fn name(&self) -> &'static str {
+        match self {
+            Sink::Webhook(_) => "webhook",
+            Sink::Slack(_) => "slack",
+            Sink::Discord(_) => "discord",
+        }
+    }
+
+    fn url(&self) -> &Url {
+        match self {
+            Sink::Webhook(u) | Sink::Slack(u) | Sink::Discord(u) => u,
+        }
+    }
+
+    // Returns the next message to deliver the alerts in, along with how many
+    // of the alerts it holds. Discord messages hold as many alerts as fit,
+    // so the rest go in the messages after it.
+    fn payload(&self, alerts: &[Alert]) -> (usize, Value) {
+        match self {
+            Sink::Webhook(_) => (alerts.len(), json!({ "alerts": alerts })),
+            Sink::Slack(_) => (alerts.len(), json!({ "text": text(alerts) })),
+            Sink::Discord(_) => {
+                let mut count = 1;
+                let mut len = alerts[0].summary().chars().count();
+                for a in alerts[1..].iter() {
+                    len += 1 + a.summary().chars().count();
+                    if len > DISCORD_MAX_CONTENT {
+                        break;
+                    }
+                    count += 1;
+                }
+                // An alert which doesn't fit a message on its own is cut.
+                let content: String = text(&alerts[..count])
+                    .chars()
+                    .take(DISCORD_MAX_CONTENT)
+                    .collect();
+                (count, json!({ "content": content }))
+            }
+        }
+    }
+}
+
+fn text(alerts: &[Alert]) -> String {
+    alerts
+        .iter()
+        .map(|a| a.summary())
+        .collect::<Vec<String>>()
+        .join("\n")
+}
+
+/// A notification that an incident opened or resolved.
+#[derive(Debug, Clone, Serialize)]
+pub struct Alert {
+    pub kind: IncidentKind,
+    pub check: String,
+    pub endpoint: String,
+    pub occurrences: u64,
+    pub last_sample: String,
+    pub resolved: bool,
+    /// When the alert was raised, in seconds since the unix epoch.
+    pub at: u64,
+}
+
+impl Alert {
+    fn summary(&self) -> String {
+        let status = if self.resolved { "RESOLVED" } else { "FIRING" };
+        format!(
+            "[{}] {} of {} at {}: {} occurrences, last {}",
+            status,
+            self.kind.name(),
+            self.check,
+            self.endpoint,
+            self.occurrences,
+            self.last_sample
+        )
+    }
+}
+
+lazy_static! {
+    static ref SENDER: Mutex<Option<UnboundedSender<Alert>>> = Mutex::new(None);
+}
+
+/// Queues an alert for an incident which opened or resolved, if any sinks
+/// are configured.
+pub fn notify(incident: &Incident) {
+    let sender = SENDER.lock().unwrap();
+    let sender = match sender.as_ref() {
+        Some(sender) => sender,
+        None => return,
+    };
+    let alert = Alert {
+        kind: incident.kind,
+        check: incident.check.clone(),
+        endpoint: incident.endpoint.clone(),
+        occurrences: incident.occurrences,
+        last_sample: incident.last_sample.clone(),
+        resolved: incident.resolved_at.is_some(),
+        at: incident.resolved_at.unwrap_or(incident.last_seen_at),
+    };
+    let _ = sender.send(alert);
+}
+
+/// Delivers raised alerts to every sink, each from its own task so a sink
+/// which hangs doesn't hold up the others.
+pub async fn run_alerts(sinks: Vec<Sink>, min_interval: Duration) {
+    info!("Starting alert delivery to {} sinks", sinks.len());
+    let (sender, mut receiver) = unbounded_channel();
+    *SENDER.lock().unwrap() = Some(sender);
+
+    let client = reqwest::Client::builder()
+        .timeout(DELIVERY_TIMEOUT)
+        .build()
+        .unwrap_or_default();
+    let queues: Vec<UnboundedSender<Alert>> = sinks
+        .into_iter()
+        .map(|sink| {
+            let (queue, alerts) = unbounded_channel();
+            tokio::spawn(run_sink(client.clone(), sink, alerts, min_interval));
+            queue
+        })
+        .collect();
+    while let Some(alert) = receiver.recv().await {
+        for queue in queues.iter() {
+            let _ = queue.send(alert.clone());
+        }
+    }
+}
+
+// Delivers the alerts of a sink, batching alerts raised within the minimum
+// interval between deliveries. Alerts which fail to deliver are retried on
+// the next delivery.
+async fn run_sink(
+    client: reqwest::Client,
+    sink: Sink,
+    mut alerts: UnboundedReceiver<Alert>,
+    min_interval: Duration,
+) {
+    let mut pending: Vec<Alert> = vec![];
+    let mut failed_attempts = 0;
+    let mut last_delivery: Option<Instant> = None;
+    let mut ticker = interval(Duration::from_secs(1));
+    loop {
+        ticker.tick().await;
+        while let Ok(alert) = alerts.try_recv() {
+            pending.push(alert);
+        }
+        if pending.is_empty() || last_delivery.map_or(false, |t| t.elapsed() < min_interval) {
+            continue;
+        }
+        last_delivery = Some(Instant::now());
+        if deliver(&client, &sink, &mut pending).await {
+            failed_attempts = 0;
+            continue;
+        }
+        failed_attempts += 1;
+        if failed_attempts >= MAX_DELIVERY_ATTEMPTS {
+            warn!(
+                "Dropping {} alerts to {} after {} failed deliveries",
+                pending.len(),
+                sink.name(),
+                failed_attempts
+            );
+            pending.clear();
+            failed_attempts = 0;
+        }
+    }
+}
+
+// Delivers pending alerts to a sink in as many messages as it needs,
+// removing the alerts delivered. Returns whether every alert was.
+async fn deliver(client: &reqwest::Client, sink: &Sink, pending: &mut Vec<Alert>) -> bool {
+    while !pending.is_empty() {
+        let (count, payload) = sink.payload(pending);
+        let res = client
+            .post(sink.url().clone())
+            .json(&payload)
+            .send()
+            .await
+            .and_then(|r| r.error_for_status());
+        if let Err(e) = res {
+            crate::metrics::ALERT_DELIVERY_FAILURES_TOTAL
+                .with_label_values(&[sink.name()])
+                .inc();
+            warn!(
+                "Delivering {} alerts to {} failed {:?}",
+                pending.len(),
+                sink.name(),
+                e
+            );
+            return false;
+        }
+        crate::metrics::ALERTS_SENT_TOTAL
+            .with_label_values(&[sink.name()])
+            .inc_by(count as u64);
+        pending.drain(..count);
+    }
+    true
+}
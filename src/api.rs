@@ -0,0 +1,59 @@
+use axum::{http::StatusCode, Json};
+use serde_json::{json, Value};
+
+/// Serves the health, client version, head, open incidents and last check
+/// results of every endpoint.
+pub async fn status() -> Json<Value> {
+    let incidents = crate::incidents::open();
+    let endpoints: Vec<Value> = crate::control::endpoints()
+        .iter()
+        .map(|u| {
+            let status = crate::node_status::status(u).unwrap_or_default();
+            let head = crate::head_monitor::head(u);
+            json!({
+                "endpoint": u.as_str(),
+                "health": status.health,
+                "version": status.version.map(|v| v.version),
+                "peer_id": status.identity.map(|i| i.peer_id),
+                "is_syncing": status.syncing.as_ref().map(|s| s.is_syncing),
+                "is_optimistic": status.syncing.as_ref().map(|s| s.is_optimistic),
+                "sync_distance": status.syncing.as_ref().map(|s| s.sync_distance),
+                "connected_peers": status.peers.map(|p| p.connected),
+                "head_slot": head.as_ref().map(|h| h.slot),
+                "head_root": head.map(|h| h.root),
+                "open_incidents": incidents.iter().filter(|i| i.endpoint == u.as_str()).count(),
+                "last_results": crate::history::last_results(u),
+            })
+        })
+        .collect();
+    Json(json!({ "endpoints": endpoints }))
+}
+
+/// Serves the recent runs of every check.
+pub async fn checks() -> Json<Value> {
+    Json(json!({ "checks": crate::history::checks() }))
+}
+
+/// Serves the recent mismatches along with every endpoint's response.
+pub async fn mismatches() -> Json<Value> {
+    Json(json!({ "mismatches": crate::history::mismatches() }))
+}
+
+/// Liveness of the checker itself.
+pub async fn healthz() -> &'static str {
+    "ok"
+}
+
+/// Readiness of the checker, i.e. whether its pipeline has been running,
+/// unless it was paused. The health of the beacon nodes it checks is
+/// reported by `/status` instead, as the checker is most useful when they
+/// are failing.
+pub async fn readyz() -> (StatusCode, &'static str) {
+    if !crate::control::is_paused() && !crate::history::is_ready() {
+        return (
+            StatusCode::SERVICE_UNAVAILABLE,
+            "pipeline has not run recently",
+        );
+    }
+    (StatusCode::OK, "ok")
+}
@@ -1,7 +1,9 @@
 use eyre::Result;
 use std::future::Future;
 use std::pin::Pin;
+use std::sync::{Arc, RwLock};
 use tokio::time::Duration;
+use tracing::info;
 use url::Url;
 
 /// Turns the eyre Result type into an boxed future.
@@ -11,6 +13,10 @@ type AsyncResult = Pin<Box<dyn Future<Output = Result<()>> + Send + Sync>>;
 /// and verify the equality of the responses.
 pub type CheckerFn = Box<dyn Fn(Vec<Url>) -> AsyncResult + Send + Sync>;
 
+/// The endpoints to request, shared with the monitors and the control API
+/// so endpoints can be added and removed at runtime.
+pub type SharedEndpoints = Arc<RwLock<Vec<Url>>>;
+
 /// Forces a value a future to be boxed and send+sync for use
 /// across threads in tokio. Used to convert our simple API checker functions
 /// defined in endpoints.rs into the CheckerFn trait defined above for usage
@@ -28,21 +34,25 @@ pub struct ApiChecker {
     /// How often to run the API checks against all endpoints.
     pub run_every: Duration,
     /// The beacon api endpoints to request.
-    endpoints: Vec<Url>,
+    endpoints: SharedEndpoints,
     /// The HTTP timeout when making requests.
     timeout: Duration,
     /// A pipeline of functions that the API checker will run
     /// against the endpoints to check for conformity.
     fns: Vec<CheckerFn>,
+    /// Whether to leave out endpoints which report themselves as syncing
+    /// instead of only annotating them in mismatch logs.
+    skip_syncing: bool,
 }
 
 impl Default for ApiChecker {
     fn default() -> Self {
         ApiChecker {
             run_every: Duration::from_millis(250),
-            endpoints: vec![],
+            endpoints: Arc::new(RwLock::new(vec![])),
             fns: vec![],
             timeout: Duration::from_secs(10),
+            skip_syncing: false,
         }
     }
 }
@@ -56,7 +66,7 @@ impl ApiChecker {
         self
     }
     pub fn endpoints(mut self, items: Vec<Url>) -> ApiChecker {
-        self.endpoints = items;
+        self.endpoints = Arc::new(RwLock::new(items));
         self
     }
     pub fn timeout(mut self, timeout: Duration) -> ApiChecker {
@@ -67,12 +77,40 @@ impl ApiChecker {
         self.fns = fns;
         self
     }
+    pub fn skip_syncing(mut self, skip: bool) -> ApiChecker {
+        self.skip_syncing = skip;
+        self
+    }
     pub fn build(self) -> ApiChecker {
+        // A node which never answers must not stall the whole pipeline,
+        // so every request to a node is bounded by the timeout.
+        crate::http::set_timeout(self.timeout);
         self
     }
+    pub fn set_pipeline(&mut self, fns: Vec<CheckerFn>) {
+        self.fns = fns;
+    }
+    pub fn shared_endpoints(&self) -> SharedEndpoints {
+        self.endpoints.clone()
+    }
     pub async fn run_pipeline(&self) -> Result<()> {
+        let endpoints = self.endpoints.read().unwrap().clone();
+        let endpoints: Vec<Url> = if self.skip_syncing {
+            endpoints
+                .into_iter()
+                .filter(|u| {
+                    let syncing = crate::node_status::is_syncing(u);
+                    if syncing {
+                        info!("Skipping syncing endpoint={}", u);
+                    }
+                    !syncing
+                })
+                .collect()
+        } else {
+            endpoints
+        };
         for f in self.fns.iter() {
-            f(self.endpoints.clone()).await?;
+            f(endpoints.clone()).await?;
         }
         Ok(())
     }
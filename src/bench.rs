@@ -0,0 +1,387 @@
+use eyre::Result;
+use rand::seq::SliceRandom;
+use std::collections::BTreeMap;
+use std::sync::{Arc, Mutex};
+use tokio::time::{interval, sleep, Duration, Instant, MissedTickBehavior};
+use tracing::info;
+use url::Url;
+
+/// How much throughput has to grow when concurrency grows for an endpoint
+/// to not yet be considered saturated.
+const SATURATION_GROWTH: f64 = 1.05;
+
+/// Drives load against every endpoint and reports how each keeps up.
+#[derive(clap::Args, Debug)]
+pub struct BenchArgs {
+    /// How many requests to keep in flight per endpoint once ramped up.
+    #[arg(long, default_value_t = 16)]
+    concurrency: usize,
+    /// The most requests per second to send per endpoint, unlimited if unset.
+    #[arg(long)]
+    rate: Option<u32>,
+    /// How long to run the benchmark for, in millis.
+    #[arg(long, value_parser = crate::parse_duration, default_value = "60000")]
+    duration: Duration,
+    /// How long to take to ramp up from one request in flight to the full
+    /// concurrency, in millis.
+    #[arg(long, value_parser = crate::parse_duration, default_value = "30000")]
+    ramp_up: Duration,
+    /// How often to sample throughput to find saturation points, in millis.
+    #[arg(long, value_parser = crate::parse_duration, default_value = "5000")]
+    step: Duration,
+    /// The HTTP timeout of each request, in millis.
+    #[arg(long, value_parser = crate::parse_duration, default_value = "10000")]
+    timeout: Duration,
+    /// The checks whose requests make up the load, picked from at random.
+    #[arg(
+        long,
+        value_enum,
+        value_delimiter = ',',
+        default_value = "state-root,finality-checkpoints,validators,balances,block,header"
+    )]
+    mix: Vec<BenchRoute>,
+}
+
+/// The requests of a check which the benchmark can send.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, clap::ValueEnum)]
+pub enum BenchRoute {
+    StateRoot,
+    FinalityCheckpoints,
+    Validators,
+    Balances,
+    Block,
+    Header,
+    ProposerDuties,
+}
+
+impl BenchRoute {
+    fn name(&self) -> &'static str {
+        match self {
+            BenchRoute::StateRoot => "state_root",
+            BenchRoute::FinalityCheckpoints => "finality_checkpoints",
+            BenchRoute::Validators => "validators",
+            BenchRoute::Balances => "balances",
+            BenchRoute::Block => "block",
+            BenchRoute::Header => "header",
+            BenchRoute::ProposerDuties => "proposer_duties",
+        }
+    }
+
+    // Builds a request path with random parameters, like the checks do.
+    fn path(&self) -> String {
+        let id = crate::endpoints::random_settled_id();
+        let indices = || {
+            crate::endpoints::random_indices()
+                .iter()
+                .map(|i| i.to_string())
+                .collect::<Vec<String>>()
+                .join(",")
+        };
+        match self {
+            BenchRoute::StateRoot => format!("eth/v1/beacon/states/{}/root", id),
+            BenchRoute::FinalityCheckpoints => {
+                format!("eth/v1/beacon/states/{}/finality_checkpoints", id)
+            }
+            BenchRoute::Validators => {
+                format!("eth/v1/beacon/states/{}/validators?id={}", id, indices())
+            }
+            BenchRoute::Balances => format!(
+                "eth/v1/beacon/states/{}/validator_balances?id={}",
+                id,
+                indices()
+            ),
+            BenchRoute::Block => format!("eth/v2/beacon/blocks/{}", id),
+            BenchRoute::Header => format!("eth/v1/beacon/headers/{}", id),
+            BenchRoute::ProposerDuties => format!(
+                "eth/v1/validator/duties/proposer/{}",
+                crate::endpoints::random_duties_epoch()
+            ),
+        }
+    }
+}
+
+/// The outcome of a single request.
+struct Sample {
+    route: BenchRoute,
+    /// The index of the throughput step the request completed in.
+    step: usize,
+    millis: f64,
+    ok: bool,
+}
+
+/// The results of an endpoint over the whole benchmark.
+struct Report {
+    endpoint: Url,
+    throughput: f64,
+    p50: f64,
+    p95: f64,
+    p99: f64,
+    max: f64,
+    error_rate: f64,
+    /// The concurrency and throughput past which adding load stopped
+    /// increasing throughput.
+    saturation: Option<(usize, f64)>,
+    /// The p99 latency of each route in the mix.
+    routes: BTreeMap<BenchRoute, f64>,
+}
+
+/// Runs the benchmark against every endpoint at once and prints a report
+/// comparing them.
+pub async fn run(endpoints: Vec<Url>, args: BenchArgs) -> Result<()> {
+    info!(
+        "Benchmarking {} endpoints for {:?} at concurrency={} rate={:?}",
+        endpoints.len(),
+        args.duration,
+        args.concurrency,
+        args.rate,
+    );
+    let args = Arc::new(args);
+    let client = reqwest::Client::builder().timeout(args.timeout).build()?;
+    let start = Instant::now();
+    let mut handles = vec![];
+    for u in endpoints.iter() {
+        handles.push(tokio::spawn(bench_endpoint(
+            u.clone(),
+            client.clone(),
+            args.clone(),
+            start,
+        )));
+    }
+    let mut reports = vec![];
+    for (u, handle) in endpoints.iter().zip(handles) {
+        let samples = handle.await?;
+        reports.push(report(u, &samples, &args));
+    }
+    print_reports(&mut reports, &args.mix);
+    Ok(())
+}
+
+async fn bench_endpoint(
+    u: Url,
+    client: reqwest::Client,
+    args: Arc<BenchArgs>,
+    start: Instant,
+) -> Vec<Sample> {
+    let samples = Arc::new(Mutex::new(vec![]));
+    let limiter = args.rate.map(|rate| {
+        let mut ticker = interval(Duration::from_secs_f64(1.0 / rate.max(1) as f64));
+        ticker.set_missed_tick_behavior(MissedTickBehavior::Delay);
+        Arc::new(tokio::sync::Mutex::new(ticker))
+    });
+    let deadline = start + args.duration;
+    let mut workers = vec![];
+    for i in 0..args.concurrency {
+        // Workers start one after the other over the ramp up.
+        let delay = args.ramp_up.mul_f64(i as f64 / args.concurrency as f64);
+        let (u, client, args, samples, limiter) = (
+            u.clone(),
+            client.clone(),
+            args.clone(),
+            samples.clone(),
+            limiter.clone(),
+        );
+        workers.push(tokio::spawn(async move {
+            sleep(delay).await;
+            while Instant::now() < deadline {
+                if let Some(limiter) = &limiter {
+                    limiter.lock().await.tick().await;
+                }
+                let route = *args.mix.choose(&mut rand::thread_rng()).unwrap();
+                let sample = request(&u, &client, route, start, args.step).await;
+                samples.lock().unwrap().push(sample);
+            }
+        }));
+    }
+    futures::future::join_all(workers).await;
+    let samples = std::mem::take(&mut *samples.lock().unwrap());
+    samples
+}
+
+async fn request(
+    u: &Url,
+    client: &reqwest::Client,
+    route: BenchRoute,
+    start: Instant,
+    step: Duration,
+) -> Sample {
+    let sent = Instant::now();
+    let ok = match crate::http::target(u).join(&route.path()) {
+        Ok(target) => match client.get(target).send().await {
+            // Read the whole body, as a node is only done once it sent it.
+            Ok(response) => response.status().is_success() && response.bytes().await.is_ok(),
+            Err(_) => false,
+        },
+        Err(_) => false,
+    };
+    Sample {
+        route,
+        step: (start.elapsed().as_millis() / step.as_millis().max(1)) as usize,
+        millis: sent.elapsed().as_secs_f64() * 1000.0,
+        ok,
+    }
+}
+
+fn report(u: &Url, samples: &[Sample], args: &BenchArgs) -> Report {
+    let mut latencies: Vec<f64> = samples.iter().filter(|s| s.ok).map(|s| s.millis).collect();
+    latencies.sort_by(|a, b| a.total_cmp(b));
+    let errors = samples.iter().filter(|s| !s.ok).count();
+    let mut routes = BTreeMap::new();
+    for route in args.mix.iter() {
+        let mut route_latencies: Vec<f64> = samples
+            .iter()
+            .filter(|s| s.ok && s.route == *route)
+            .map(|s| s.millis)
+            .collect();
+        route_latencies.sort_by(|a, b| a.total_cmp(b));
+        routes.insert(*route, percentile(&route_latencies, 0.99));
+    }
+    Report {
+        endpoint: u.clone(),
+        throughput: latencies.len() as f64 / args.duration.as_secs_f64(),
+        p50: percentile(&latencies, 0.50),
+        p95: percentile(&latencies, 0.95),
+        p99: percentile(&latencies, 0.99),
+        max: latencies.last().copied().unwrap_or_default(),
+        error_rate: errors as f64 / samples.len().max(1) as f64,
+        saturation: saturation(samples, args),
+        routes,
+    }
+}
+
+// Finds the first step in which more requests were in flight than in the
+// previous one without throughput growing accordingly.
+fn saturation(samples: &[Sample], args: &BenchArgs) -> Option<(usize, f64)> {
+    let steps = (args.duration.as_millis() / args.step.as_millis().max(1)) as usize;
+    let mut throughputs = vec![0.0; steps];
+    for s in samples.iter().filter(|s| s.ok && s.step < steps) {
+        throughputs[s.step] += 1.0 / args.step.as_secs_f64();
+    }
+    let concurrency = |step: usize| {
+        let elapsed = args.step * (step as u32 + 1);
+        if args.ramp_up.is_zero() {
+            return args.concurrency;
+        }
+        let ramped = elapsed.as_secs_f64() / args.ramp_up.as_secs_f64();
+        ((ramped * args.concurrency as f64).ceil() as usize).clamp(1, args.concurrency)
+    };
+    (1..steps).find_map(|k| {
+        let (previous, current) = (throughputs[k - 1], throughputs[k]);
+        if concurrency(k) > concurrency(k - 1) && current < previous * SATURATION_GROWTH {
+            Some((concurrency(k - 1), previous))
+        } else {
+            None
+        }
+    })
+}
+
+fn percentile(sorted: &[f64], p: f64) -> f64 {
+    if sorted.is_empty() {
+        return 0.0;
+    }
+    let idx = ((p * sorted.len() as f64).ceil() as usize).clamp(1, sorted.len()) - 1;
+    sorted[idx]
+}
+
+fn print_reports(reports: &mut [Report], mix: &[BenchRoute]) {
+    // The endpoint sustaining the most load comes first.
+    reports.sort_by(|a, b| b.throughput.total_cmp(&a.throughput));
+    println!(
+        "{:<40} {:>10} {:>10} {:>10} {:>10} {:>10} {:>8} {:>20}",
+        "endpoint", "req/s", "p50 ms", "p95 ms", "p99 ms", "max ms", "errors", "saturation"
+    );
+    for r in reports.iter() {
+        let saturation = match r.saturation {
+            Some((concurrency, throughput)) => {
+                format!("{} @ {:.1} req/s", concurrency, throughput)
+            }
+            None => "none".to_string(),
+        };
+        println!(
+            "{:<40} {:>10.1} {:>10.1} {:>10.1} {:>10.1} {:>10.1} {:>7.2}% {:>20}",
+            r.endpoint.as_str(),
+            r.throughput,
+            r.p50,
+            r.p95,
+            r.p99,
+            r.max,
+            r.error_rate * 100.0,
+            saturation,
+        );
+    }
+    println!();
+    print!("{:<40}", "p99 ms by route");
+    for route in mix.iter() {
+        print!(" {:>20}", route.name());
+    }
+    println!();
+    for r in reports.iter() {
+        print!("{:<40}", r.endpoint.as_str());
+        for route in mix.iter() {
+            print!(
+                " {:>20.1}",
+                r.routes.get(route).copied().unwrap_or_default()
+            );
+        }
+        println!();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn args(duration: u64, ramp_up: u64, step: u64) -> BenchArgs {
+        BenchArgs {
+            concurrency: 4,
+            rate: None,
+            duration: Duration::from_secs(duration),
+            ramp_up: Duration::from_secs(ramp_up),
+            step: Duration::from_secs(step),
+            timeout: Duration::from_secs(10),
+            mix: vec![BenchRoute::StateRoot],
+        }
+    }
+
+    // Completes the given number of requests in each step.
+    fn samples(per_step: &[usize]) -> Vec<Sample> {
+        per_step
+            .iter()
+            .enumerate()
+            .flat_map(|(step, n)| {
+                (0..*n).map(move |_| Sample {
+                    route: BenchRoute::StateRoot,
+                    step,
+                    millis: 1.0,
+                    ok: true,
+                })
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_percentile() {
+        assert_eq!(percentile(&[], 0.99), 0.0);
+        let sorted: Vec<f64> = (1..=100).map(|i| i as f64).collect();
+        assert_eq!(percentile(&sorted, 0.0), 1.0);
+        assert_eq!(percentile(&sorted, 0.5), 50.0);
+        assert_eq!(percentile(&sorted, 0.99), 99.0);
+        assert_eq!(percentile(&sorted, 1.0), 100.0);
+        assert_eq!(percentile(&[7.0], 0.5), 7.0);
+    }
+
+    #[test]
+    fn test_saturation() {
+        // Concurrency ramps from 1 to 4 over the four steps, but throughput
+        // stops growing past 2 requests in flight.
+        let (concurrency, throughput) =
+            saturation(&samples(&[10, 20, 20, 20]), &args(20, 20, 5)).unwrap();
+        assert_eq!(concurrency, 2);
+        assert!((throughput - 4.0).abs() < 1e-9);
+        assert!(saturation(&samples(&[10, 20, 30, 40]), &args(20, 20, 5)).is_none());
+        assert!(saturation(&[], &args(20, 20, 5)).is_none());
+        // Without a ramp up concurrency never grows.
+        assert!(saturation(&samples(&[10, 20, 20, 20]), &args(20, 0, 5)).is_none());
+        // A step longer than the benchmark has no throughput to compare.
+        assert!(saturation(&samples(&[10]), &args(5, 5, 10)).is_none());
+    }
+}
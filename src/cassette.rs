@@ -0,0 +1,465 @@
+use crate::http::is_id;
+use axum::{
+    body::Bytes,
+    extract::State,
+    http::{
+        header::{ACCEPT, CONTENT_TYPE},
+        HeaderMap, Method, StatusCode, Uri,
+    },
+    response::{IntoResponse, Response},
+    Router,
+};
+use eyre::Result;
+use serde::{Deserialize, Serialize};
+use std::fs::{File, OpenOptions};
+use std::io::{BufRead, BufReader, Write};
+use std::net::SocketAddr;
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+use tracing::{info, warn};
+use url::Url;
+
+/// Responses larger than this are passed through without being recorded,
+/// as full states would add hundreds of megabytes to the cassette every run.
+const MAX_RECORDED_BODY_LEN: usize = 8 << 20;
+
+/// A request to a beacon node and the response it gave, stored one per line
+/// in a cassette file.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Interaction {
+    /// The real endpoint the request was sent to.
+    pub endpoint: String,
+    pub method: String,
+    /// The path of the request along with its query.
+    pub path: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub request_body: Option<String>,
+    pub status: u16,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub content_type: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub version: Option<String>,
+    /// The response body, hex encoded if it is not valid UTF-8 such as SSZ.
+    pub body: String,
+    #[serde(default)]
+    pub hex: bool,
+}
+
+impl Interaction {
+    fn body_bytes(&self) -> Vec<u8> {
+        if self.hex {
+            hex::decode(&self.body).unwrap_or_default()
+        } else {
+            self.body.clone().into_bytes()
+        }
+    }
+
+    fn to_response(&self) -> Response {
+        response(
+            self.status,
+            &self.content_type,
+            &self.version,
+            self.body_bytes(),
+        )
+    }
+}
+
+fn response(
+    status: u16,
+    content_type: &Option<String>,
+    version: &Option<String>,
+    body: impl IntoResponse,
+) -> Response {
+    let status = StatusCode::from_u16(status).unwrap_or(StatusCode::OK);
+    let mut headers = HeaderMap::new();
+    if let Some(content_type) = content_type.as_ref().and_then(|v| v.parse().ok()) {
+        headers.insert(CONTENT_TYPE, content_type);
+    }
+    if let Some(version) = version.as_ref().and_then(|v| v.parse().ok()) {
+        headers.insert(crate::http::CONSENSUS_VERSION_HEADER, version);
+    }
+    (status, headers, body).into_response()
+}
+
+/// Starts a proxy in front of every endpoint which records every request and
+/// response passing through it to a cassette file, and sends the requests
+/// to the endpoints through it.
+pub async fn record(endpoints: Vec<Url>, path: &Path) -> Result<()> {
+    let file = OpenOptions::new().create(true).append(true).open(path)?;
+    let file = Arc::new(Mutex::new(file));
+    for u in endpoints.into_iter() {
+        let recorder = Arc::new(Recorder {
+            endpoint: u.clone(),
+            client: reqwest::Client::new(),
+            file: file.clone(),
+        });
+        let router = Router::new().fallback(proxy).with_state(recorder);
+        let proxy_url = serve(router);
+        info!(
+            "Recording endpoint={} through {} to {}",
+            u,
+            proxy_url,
+            path.display()
+        );
+        crate::http::proxy(u, proxy_url);
+    }
+    Ok(())
+}
+
+/// Starts a server for every endpoint recorded in a cassette file which
+/// replays its responses, returning the recorded endpoints, whose requests
+/// are sent to the servers.
+pub async fn playback(path: &Path) -> Result<Vec<Url>> {
+    let reader = BufReader::new(File::open(path)?);
+    let mut cassettes: Vec<(String, Vec<Interaction>)> = vec![];
+    for line in reader.lines() {
+        let line = line?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        let interaction: Interaction = serde_json::from_str(&line)?;
+        match cassettes
+            .iter_mut()
+            .find(|(endpoint, _)| *endpoint == interaction.endpoint)
+        {
+            Some((_, interactions)) => interactions.push(interaction),
+            None => cassettes.push((interaction.endpoint.clone(), vec![interaction])),
+        }
+    }
+    let cassette = Arc::new(Cassette {
+        endpoints: cassettes.iter().map(|(e, _)| e.clone()).collect(),
+        interactions: cassettes.into_iter().flat_map(|(_, i)| i).collect(),
+    });
+    let mut urls = vec![];
+    for endpoint in cassette.endpoints.iter() {
+        let url = Url::parse(endpoint)?;
+        info!(
+            "Replaying {} interactions of endpoint={} from {}",
+            cassette
+                .interactions
+                .iter()
+                .filter(|i| i.endpoint == *endpoint)
+                .count(),
+            url,
+            path.display()
+        );
+        let replayer = Replayer {
+            endpoint: endpoint.clone(),
+            cassette: cassette.clone(),
+        };
+        let router = Router::new()
+            .fallback(replay)
+            .with_state(Arc::new(replayer));
+        crate::http::proxy(url.clone(), serve(router));
+        urls.push(url);
+    }
+    Ok(urls)
+}
+
+fn serve(router: Router) -> Url {
+    let addr: SocketAddr = "127.0.0.1:0".parse().unwrap();
+    let server = axum::Server::bind(&addr).serve(router.into_make_service());
+    let url = Url::parse(&format!("http://{}/", server.local_addr())).unwrap();
+    tokio::spawn(server);
+    url
+}
+
+// The interactions of every endpoint in a cassette, shared by the servers
+// replaying them.
+struct Cassette {
+    endpoints: Vec<String>,
+    interactions: Vec<Interaction>,
+}
+
+struct Replayer {
+    endpoint: String,
+    cassette: Arc<Cassette>,
+}
+
+struct Recorder {
+    endpoint: Url,
+    client: reqwest::Client,
+    file: Arc<Mutex<File>>,
+}
+
+impl Recorder {
+    fn write(&self, interaction: &Interaction) {
+        let line = match serde_json::to_string(interaction) {
+            Ok(line) => line,
+            Err(e) => {
+                warn!("Failed to encode interaction {:?}", e);
+                return;
+            }
+        };
+        if let Err(e) = writeln!(self.file.lock().unwrap(), "{}", line) {
+            warn!("Failed to record interaction {:?}", e);
+        }
+    }
+}
+
+async fn proxy(
+    State(recorder): State<Arc<Recorder>>,
+    method: Method,
+    uri: Uri,
+    headers: HeaderMap,
+    body: Bytes,
+) -> Response {
+    let path = uri
+        .path_and_query()
+        .map(|p| p.as_str())
+        .unwrap_or_else(|| uri.path());
+    // Event streams never end, so they can't be recorded as a single response.
+    if uri.path().starts_with("/eth/v1/events") {
+        return (
+            StatusCode::NOT_IMPLEMENTED,
+            "event streams are not recorded",
+        )
+            .into_response();
+    }
+    let target = match recorder.endpoint.join(&path[1..]) {
+        Ok(target) => target,
+        Err(e) => return (StatusCode::BAD_REQUEST, e.to_string()).into_response(),
+    };
+    let mut request = recorder.client.request(method.clone(), target);
+    for (name, value) in headers.iter() {
+        if *name == ACCEPT || *name == CONTENT_TYPE {
+            request = request.header(name, value);
+        }
+    }
+    if !body.is_empty() {
+        request = request.body(body.clone());
+    }
+    let response = match request.send().await {
+        Ok(response) => response,
+        Err(e) => {
+            warn!(
+                "Request method={} endpoint={} failed {:?}",
+                path, recorder.endpoint, e
+            );
+            return (StatusCode::BAD_GATEWAY, e.to_string()).into_response();
+        }
+    };
+    let header = |name: &str| {
+        response
+            .headers()
+            .get(name)
+            .and_then(|v| v.to_str().ok())
+            .map(|v| v.to_string())
+    };
+    let content_type = header(CONTENT_TYPE.as_str());
+    let version = header(crate::http::CONSENSUS_VERSION_HEADER);
+    let status = response.status().as_u16();
+    let bytes = match response.bytes().await {
+        Ok(bytes) => bytes,
+        Err(e) => return (StatusCode::BAD_GATEWAY, e.to_string()).into_response(),
+    };
+    if bytes.len() > MAX_RECORDED_BODY_LEN {
+        info!(
+            "Not recording response of {} bytes method={} endpoint={}",
+            bytes.len(),
+            path,
+            recorder.endpoint
+        );
+        return response(status, &content_type, &version, bytes);
+    }
+    let (body_text, is_hex) = match std::str::from_utf8(&bytes) {
+        Ok(text) => (text.to_string(), false),
+        Err(_) => (hex::encode(&bytes), true),
+    };
+    let interaction = Interaction {
+        endpoint: recorder.endpoint.to_string(),
+        method: method.to_string(),
+        path: path.to_string(),
+        request_body: (!body.is_empty()).then(|| String::from_utf8_lossy(&body).to_string()),
+        status,
+        content_type,
+        version,
+        body: body_text,
+        hex: is_hex,
+    };
+    recorder.write(&interaction);
+    interaction.to_response()
+}
+
+async fn replay(
+    State(replayer): State<Arc<Replayer>>,
+    method: Method,
+    uri: Uri,
+    body: Bytes,
+) -> Response {
+    let path = uri
+        .path_and_query()
+        .map(|p| p.as_str())
+        .unwrap_or_else(|| uri.path());
+    let body = (!body.is_empty()).then(|| String::from_utf8_lossy(&body).to_string());
+    match find(
+        &replayer.cassette,
+        &replayer.endpoint,
+        method.as_str(),
+        path,
+        &body,
+    ) {
+        Some(interaction) => interaction.to_response(),
+        None => (
+            StatusCode::NOT_FOUND,
+            format!("no recorded interaction for {} {}", method, path),
+        )
+            .into_response(),
+    }
+}
+
+// Finds the recorded interaction of an endpoint for a request. The checks
+// pick random ids every run, so requests for an id which no endpoint
+// recorded are answered with the last recording of the same route for
+// another id. Every endpoint must replay the same id, or replaying would
+// report mismatches which never happened, so that id must have been
+// recorded for all of them.
+fn find<'a>(
+    cassette: &'a Cassette,
+    endpoint: &str,
+    method: &str,
+    path: &str,
+    body: &Option<String>,
+) -> Option<&'a Interaction> {
+    let recorded = |e: &str, p: &str| {
+        cassette
+            .interactions
+            .iter()
+            .rev()
+            .find(move |i| i.endpoint == e && i.method == method && i.path == p)
+    };
+    let exact = cassette.interactions.iter().rev().find(|i| {
+        i.endpoint == endpoint && i.method == method && i.path == path && i.request_body == *body
+    });
+    if exact.is_some() {
+        return exact;
+    }
+    // Another recording of the same id would answer a different request,
+    // and an endpoint whose request failed while recording has none.
+    if cassette
+        .interactions
+        .iter()
+        .any(|i| i.method == method && strip_query(&i.path) == strip_query(path))
+    {
+        return None;
+    }
+    let other = cassette
+        .interactions
+        .iter()
+        .rev()
+        .filter(|i| i.method == method && same_route(&i.path, path))
+        .find(|i| {
+            cassette
+                .endpoints
+                .iter()
+                .all(|e| recorded(e, &i.path).is_some())
+        })
+        .and_then(|i| recorded(endpoint, &i.path))?;
+    info!(
+        "Replaying {} {} with the recording of {}",
+        method, path, other.path
+    );
+    Some(other)
+}
+
+fn strip_query(path: &str) -> &str {
+    path.split('?').next().unwrap_or_default()
+}
+
+fn same_route(a: &str, b: &str) -> bool {
+    let (a, b) = (strip_query(a), strip_query(b));
+    let a: Vec<&str> = a.split('/').collect();
+    let b: Vec<&str> = b.split('/').collect();
+    a.len() == b.len()
+        && a.iter()
+            .zip(b.iter())
+            .all(|(x, y)| x == y || (is_id(x) && is_id(y)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::endpoints::check_finality_checkpoints;
+    use crate::mock::{finality_checkpoints, MockNode, SERIAL};
+
+    #[test]
+    fn test_same_route() {
+        assert!(same_route(
+            "/eth/v1/beacon/states/finalized/root",
+            "/eth/v1/beacon/states/123/root"
+        ));
+        assert!(same_route(
+            "/eth/v1/beacon/states/head/validators?id=1,2",
+            "/eth/v1/beacon/states/0xabcd/validators"
+        ));
+        assert!(!same_route(
+            "/eth/v1/beacon/states/head/root",
+            "/eth/v1/beacon/states/head/finality_checkpoints"
+        ));
+    }
+
+    fn interaction(endpoint: &str, path: &str, body: &str) -> Interaction {
+        Interaction {
+            endpoint: endpoint.to_string(),
+            method: "GET".to_string(),
+            path: path.to_string(),
+            request_body: None,
+            status: 200,
+            content_type: None,
+            version: None,
+            body: body.to_string(),
+            hex: false,
+        }
+    }
+
+    #[test]
+    fn test_every_endpoint_replays_the_same_id() {
+        // The second endpoint's request for slot 2 failed while recording.
+        let cassette = Cassette {
+            endpoints: vec!["a".to_string(), "b".to_string()],
+            interactions: vec![
+                interaction("a", "/eth/v1/beacon/states/1/root", "a1"),
+                interaction("b", "/eth/v1/beacon/states/1/root", "b1"),
+                interaction("a", "/eth/v1/beacon/states/2/root", "a2"),
+            ],
+        };
+        let replayed =
+            |endpoint, path| find(&cassette, endpoint, "GET", path, &None).map(|i| i.body.as_str());
+        assert_eq!(replayed("a", "/eth/v1/beacon/states/3/root"), Some("a1"));
+        assert_eq!(replayed("b", "/eth/v1/beacon/states/3/root"), Some("b1"));
+        assert_eq!(replayed("a", "/eth/v1/beacon/states/2/root"), Some("a2"));
+        assert_eq!(replayed("b", "/eth/v1/beacon/states/2/root"), None);
+    }
+
+    #[tokio::test]
+    async fn test_recorded_mismatch_replays() {
+        let _guard = SERIAL.lock().await;
+        let nodes = vec![MockNode::start().await, MockNode::start().await];
+        nodes[1].set_fixture(
+            "/eth/v1/beacon/states/{state_id}/finality_checkpoints",
+            finality_checkpoints(
+                1,
+                "0x0303030303030303030303030303030303030303030303030303030303030303",
+            ),
+        );
+        let path = std::env::temp_dir().join(format!("cassette-{}.jsonl", std::process::id()));
+        let _ = std::fs::remove_file(&path);
+
+        let endpoints: Vec<Url> = nodes.iter().map(|n| n.url.clone()).collect();
+        record(endpoints.clone(), &path).await.unwrap();
+        let before = crate::metrics::CHECKPOINT_NOT_EQUAL_TOTAL.get();
+        check_finality_checkpoints(endpoints.clone()).await.unwrap();
+        assert_eq!(crate::metrics::CHECKPOINT_NOT_EQUAL_TOTAL.get(), before + 1);
+
+        // The nodes no longer answer, but the mismatch is reproduced from the cassette.
+        for n in nodes.iter() {
+            n.fail(Some(StatusCode::INTERNAL_SERVER_ERROR));
+        }
+        let replayed = playback(&path).await.unwrap();
+        // The endpoints keep their own urls, only their requests are replayed.
+        assert_eq!(replayed, endpoints);
+        check_finality_checkpoints(replayed).await.unwrap();
+        assert_eq!(crate::metrics::CHECKPOINT_NOT_EQUAL_TOTAL.get(), before + 2);
+        let _ = std::fs::remove_file(&path);
+    }
+}
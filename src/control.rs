@@ -0,0 +1,341 @@
+use crate::api_checker::{force_boxed, CheckerFn, SharedEndpoints};
+use crate::endpoints::*;
+use crate::history::CheckRun;
+use crate::types::{BlockIdExt, StateIdExt};
+use axum::{
+    http::{header::AUTHORIZATION, HeaderMap, StatusCode},
+    Json,
+};
+use beacon_api_client::{BlockId, StateId};
+use ethereum_consensus::primitives::{Epoch, Root, ValidatorIndex};
+use eyre::{eyre, Result};
+use lazy_static::lazy_static;
+use serde::Deserialize;
+use serde_json::{json, Value};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex, RwLock};
+use tokio::time::{Duration, Instant};
+use tracing::info;
+use url::Url;
+
+type ControlResult = Result<Json<Value>, (StatusCode, String)>;
+
+/// What the control API needs from the checker it controls.
+struct Settings {
+    /// The bearer token requests must present. The control API is disabled
+    /// without one.
+    token: Option<String>,
+    endpoints: SharedEndpoints,
+    /// How long a triggered check may take, across all of its requests.
+    timeout: Duration,
+}
+
+lazy_static! {
+    static ref SETTINGS: RwLock<Settings> = RwLock::new(Settings {
+        token: None,
+        endpoints: Arc::new(RwLock::new(vec![])),
+        timeout: Duration::from_secs(600),
+    });
+}
+
+static PAUSED: AtomicBool = AtomicBool::new(false);
+
+tokio::task_local! {
+    static TRIGGER: Trigger;
+}
+
+/// Parameters a triggered check uses instead of random ones.
+#[derive(Clone, Default)]
+pub struct Params {
+    pub state_id: Option<StateIdExt>,
+    pub block_id: Option<BlockIdExt>,
+    pub indices: Option<Vec<ValidatorIndex>>,
+    pub epoch: Option<Epoch>,
+}
+
+/// A check run on demand, collecting the comparisons it makes.
+struct Trigger {
+    params: Params,
+    runs: Mutex<Vec<CheckRun>>,
+}
+
+/// A request to run a check immediately.
+#[derive(Debug, Deserialize)]
+pub struct TriggerRequest {
+    /// The name of the check, e.g. `validators` for `check_validators`.
+    check: String,
+    /// A state id such as `head`, `finalized`, a slot or a 0x prefixed root.
+    state_id: Option<String>,
+    /// A block id such as `head`, `finalized`, a slot or a 0x prefixed root.
+    block_id: Option<String>,
+    indices: Option<Vec<usize>>,
+    /// The epoch of duty and reward checks.
+    epoch: Option<Epoch>,
+    /// The endpoints to check, every endpoint if unset.
+    endpoints: Option<Vec<Url>>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct EndpointRequest {
+    url: Url,
+}
+
+/// Enables the control API with the token requests must present, over the
+/// endpoints of the checker.
+pub fn configure(token: Option<String>, endpoints: SharedEndpoints, timeout: Duration) {
+    *SETTINGS.write().unwrap() = Settings {
+        token,
+        endpoints,
+        timeout,
+    };
+}
+
+/// The endpoints being checked, including those not polled yet.
+pub fn endpoints() -> Vec<Url> {
+    SETTINGS.read().unwrap().endpoints.read().unwrap().clone()
+}
+
+/// Whether the checker's pipeline was paused.
+pub fn is_paused() -> bool {
+    PAUSED.load(Ordering::Relaxed)
+}
+
+/// The parameters of the check being triggered, if the current task is
+/// running one.
+pub fn params() -> Option<Params> {
+    TRIGGER.try_with(|t| t.params.clone()).ok()
+}
+
+/// Whether the current task is running a triggered check, which must not
+/// affect incidents, history or metrics of the scheduled runs.
+pub fn is_triggered() -> bool {
+    TRIGGER.try_with(|_| ()).is_ok()
+}
+
+/// Collects a comparison made by a triggered check, so it can be returned
+/// to whoever triggered it.
+pub fn collect(run: &CheckRun) {
+    let _ = TRIGGER.try_with(|t| t.runs.lock().unwrap().push(run.clone()));
+}
+
+// Rejects requests when the control API is disabled or the request does
+// not present its token.
+fn authorize(headers: &HeaderMap) -> Result<(), (StatusCode, String)> {
+    let settings = SETTINGS.read().unwrap();
+    let token = match &settings.token {
+        Some(token) => token,
+        None => return Err((StatusCode::NOT_FOUND, "control API is disabled".to_string())),
+    };
+    let presented = headers
+        .get(AUTHORIZATION)
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.strip_prefix("Bearer "))
+        .unwrap_or_default();
+    if !constant_time_eq(presented.as_bytes(), token.as_bytes()) {
+        return Err((StatusCode::UNAUTHORIZED, "invalid token".to_string()));
+    }
+    Ok(())
+}
+
+fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    a.len() == b.len() && a.iter().zip(b).fold(0, |acc, (x, y)| acc | (x ^ y)) == 0
+}
+
+/// Pauses the checker's pipeline. Monitors keep running.
+pub async fn pause(headers: HeaderMap) -> ControlResult {
+    authorize(&headers)?;
+    PAUSED.store(true, Ordering::Relaxed);
+    info!("Paused API checker pipeline");
+    Ok(Json(json!({ "paused": true })))
+}
+
+/// Resumes the checker's pipeline.
+pub async fn resume(headers: HeaderMap) -> ControlResult {
+    authorize(&headers)?;
+    PAUSED.store(false, Ordering::Relaxed);
+    info!("Resumed API checker pipeline");
+    Ok(Json(json!({ "paused": false })))
+}
+
+/// Runs a check immediately with the given parameters and returns the
+/// comparisons it made.
+pub async fn trigger(headers: HeaderMap, Json(req): Json<TriggerRequest>) -> ControlResult {
+    authorize(&headers)?;
+    let bad_request = |e: eyre::Report| (StatusCode::BAD_REQUEST, e.to_string());
+    let f = check(&req.check).ok_or_else(|| bad_request(eyre!("unknown check {}", req.check)))?;
+    let params = Params {
+        state_id: req
+            .state_id
+            .as_deref()
+            .map(parse_state_id)
+            .transpose()
+            .map_err(bad_request)?,
+        block_id: req
+            .block_id
+            .as_deref()
+            .map(parse_block_id)
+            .transpose()
+            .map_err(bad_request)?,
+        indices: req
+            .indices
+            .map(|indices| indices.into_iter().map(ValidatorIndex::from).collect()),
+        epoch: req.epoch,
+    };
+    let (endpoints, timeout) = {
+        let settings = SETTINGS.read().unwrap();
+        let endpoints = settings.endpoints.read().unwrap().clone();
+        (req.endpoints.unwrap_or(endpoints), settings.timeout)
+    };
+    info!(
+        "Triggering check={} endpoints={}",
+        req.check,
+        endpoints.len()
+    );
+    let trigger = Trigger {
+        params,
+        runs: Mutex::new(vec![]),
+    };
+    let start = Instant::now();
+    let (runs, res) = TRIGGER
+        .scope(trigger, async move {
+            let res = tokio::time::timeout(timeout, f(endpoints)).await;
+            (TRIGGER.with(|t| t.runs.lock().unwrap().clone()), res)
+        })
+        .await;
+    let error = match res {
+        Ok(Ok(())) => None,
+        Ok(Err(e)) => Some(e.to_string()),
+        Err(_) => Some(format!("timed out after {:?}", timeout)),
+    };
+    Ok(Json(json!({
+        "check": req.check,
+        "elapsed_millis": start.elapsed().as_millis() as u64,
+        "error": error,
+        "runs": runs,
+    })))
+}
+
+/// Lists the endpoints being checked.
+pub async fn list_endpoints(headers: HeaderMap) -> ControlResult {
+    authorize(&headers)?;
+    let settings = SETTINGS.read().unwrap();
+    let endpoints = settings.endpoints.read().unwrap().clone();
+    Ok(Json(json!({ "endpoints": endpoints })))
+}
+
+/// Starts checking an endpoint, from the next run of the pipeline on.
+pub async fn add_endpoint(headers: HeaderMap, Json(req): Json<EndpointRequest>) -> ControlResult {
+    authorize(&headers)?;
+    let settings = SETTINGS.read().unwrap();
+    let mut endpoints = settings.endpoints.write().unwrap();
+    if !endpoints.contains(&req.url) {
+        info!("Adding endpoint={}", req.url);
+        endpoints.push(req.url);
+    }
+    Ok(Json(json!({ "endpoints": *endpoints })))
+}
+
+/// Stops checking an endpoint and drops what is known about it.
+pub async fn remove_endpoint(
+    headers: HeaderMap,
+    Json(req): Json<EndpointRequest>,
+) -> ControlResult {
+    authorize(&headers)?;
+    let settings = SETTINGS.read().unwrap();
+    let mut endpoints = settings.endpoints.write().unwrap();
+    if !endpoints.contains(&req.url) {
+        return Err((
+            StatusCode::NOT_FOUND,
+            format!("endpoint {} is not checked", req.url),
+        ));
+    }
+    info!("Removing endpoint={}", req.url);
+    endpoints.retain(|u| *u != req.url);
+    forget(&req.url);
+    Ok(Json(json!({ "endpoints": *endpoints })))
+}
+
+/// Drops everything known about an endpoint which is no longer checked,
+/// resolving its incidents and removing its metric series. Callers hold
+/// the write lock of the endpoints, so monitors which were polling it
+/// don't record it again.
+pub fn forget(endpoint: &Url) {
+    crate::node_status::forget(endpoint);
+    crate::head_monitor::forget(endpoint);
+    crate::incidents::forget(endpoint.as_str());
+    crate::history::forget(endpoint);
+    crate::slo::forget(endpoint);
+    crate::metrics::forget(endpoint.as_str());
+}
+
+/// Looks up a check by its name, which is its function's name without
+/// the `check_` prefix.
+pub fn check(name: &str) -> Option<CheckerFn> {
+    let f = match name {
+        "validators" => force_boxed(check_validators),
+        "balances" => force_boxed(check_balances),
+        "block" => force_boxed(check_block),
+        "finality_checkpoints" => force_boxed(check_finality_checkpoints),
+        "state_root" => force_boxed(check_state_root),
+        "proposer_duties" => force_boxed(check_proposer_duties),
+        "attester_duties" => force_boxed(check_attester_duties),
+        "sync_committee_duties" => force_boxed(check_sync_committee_duties),
+        "block_rewards" => force_boxed(check_block_rewards),
+        "attestation_rewards" => force_boxed(check_attestation_rewards),
+        "sync_committee_rewards" => force_boxed(check_sync_committee_rewards),
+        "light_client_bootstrap" => force_boxed(check_light_client_bootstrap),
+        "light_client_updates" => force_boxed(check_light_client_updates),
+        "light_client_finality_update" => force_boxed(check_light_client_finality_update),
+        "light_client_optimistic_update" => force_boxed(check_light_client_optimistic_update),
+        "blob_sidecars" => force_boxed(check_blob_sidecars),
+        "block_attestations" => force_boxed(check_block_attestations),
+        "pool_attestations" => force_boxed(check_pool_attestations),
+        "pool_attester_slashings" => force_boxed(check_pool_attester_slashings),
+        "pool_proposer_slashings" => force_boxed(check_pool_proposer_slashings),
+        "pool_voluntary_exits" => force_boxed(check_pool_voluntary_exits),
+        "pool_bls_to_execution_changes" => force_boxed(check_pool_bls_to_execution_changes),
+        "metadata" => force_boxed(check_metadata),
+        "response_schemas" => force_boxed(check_response_schemas),
+        "openapi_routes" => force_boxed(check_openapi_routes),
+        "error_paths" => force_boxed(check_error_paths),
+        "block_hash_tree_root" => force_boxed(check_block_hash_tree_root),
+        "state_hash_tree_root" => force_boxed(check_state_hash_tree_root),
+        "debug_state" => force_boxed(check_debug_state),
+        "block_encodings" => force_boxed(check_block_encodings),
+        "state_encodings" => force_boxed(check_state_encodings),
+        "block_ssz" => force_boxed(check_block_ssz),
+        "state_ssz" => force_boxed(check_state_ssz),
+        "blob_sidecar_encodings" => force_boxed(check_blob_sidecar_encodings),
+        "blob_sidecars_ssz" => force_boxed(check_blob_sidecars_ssz),
+        _ => return None,
+    };
+    Some(f)
+}
+
+fn parse_root(id: &str) -> Result<Root> {
+    let bytes = hex::decode(id.trim_start_matches("0x"))?;
+    Root::try_from(bytes.as_slice()).map_err(|_| eyre!("root {} is not 32 bytes", id))
+}
+
+fn parse_state_id(id: &str) -> Result<StateIdExt> {
+    let inner = match id {
+        "head" => StateId::Head,
+        "genesis" => StateId::Genesis,
+        "finalized" => StateId::Finalized,
+        "justified" => StateId::Justified,
+        id if id.starts_with("0x") => StateId::Root(parse_root(id)?),
+        id => StateId::Slot(id.parse().map_err(|_| eyre!("invalid state id {}", id))?),
+    };
+    Ok(StateIdExt { inner })
+}
+
+fn parse_block_id(id: &str) -> Result<BlockIdExt> {
+    let inner = match id {
+        "head" => BlockId::Head,
+        "genesis" => BlockId::Genesis,
+        "finalized" => BlockId::Finalized,
+        id if id.starts_with("0x") => BlockId::Root(parse_root(id)?),
+        id => BlockId::Slot(id.parse().map_err(|_| eyre!("invalid block id {}", id))?),
+    };
+    Ok(BlockIdExt { inner })
+}
@@ -0,0 +1,204 @@
+use axum::response::Html;
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt::Write;
+use url::Url;
+
+/// How often the dashboard reloads itself, in seconds.
+const REFRESH_SECS: u64 = 15;
+
+/// How many mismatches the dashboard shows.
+const MAX_MISMATCHES_SHOWN: usize = 10;
+
+const SPARKLINE_WIDTH: usize = 120;
+const SPARKLINE_HEIGHT: usize = 24;
+
+const STYLE: &str = "
+body { font-family: sans-serif; margin: 1em 2em; color: #222; }
+table { border-collapse: collapse; margin-bottom: 2em; }
+th, td { border: 1px solid #ccc; padding: 4px 8px; text-align: left; font-size: 13px; }
+td.ok { background: #d4f4d4; }
+td.mismatch { background: #f8c8c8; }
+td.unknown { background: #eee; color: #888; }
+pre { background: #f6f6f6; padding: 8px; font-size: 12px; overflow-x: auto; }
+span.removed { color: #b00; }
+span.added { color: #070; }
+small { color: #888; }
+";
+
+/// Serves a page showing the status of every check against every endpoint,
+/// their latencies, the client versions and the recent mismatches.
+pub async fn handler() -> Html<String> {
+    let endpoints = crate::control::endpoints();
+    let mut page = String::new();
+    let _ = write!(
+        page,
+        "<!DOCTYPE html><html><head><meta charset=\"utf-8\">\
+         <meta http-equiv=\"refresh\" content=\"{}\">\
+         <title>Beacon API checker</title><style>{}</style></head><body>\
+         <h1>Beacon API checker</h1>",
+        REFRESH_SECS, STYLE
+    );
+    endpoints_table(&mut page, &endpoints);
+    checks_matrix(&mut page, &endpoints);
+    latencies_matrix(&mut page, &endpoints);
+    mismatches(&mut page);
+    page.push_str("</body></html>");
+    Html(page)
+}
+
+fn endpoints_table(page: &mut String, endpoints: &[Url]) {
+    page.push_str(
+        "<h2>Endpoints</h2><table><tr><th>endpoint</th><th>version</th><th>health</th>\
+         <th>syncing</th><th>peers</th><th>head</th></tr>",
+    );
+    for u in endpoints.iter() {
+        let status = crate::node_status::status(u).unwrap_or_default();
+        let health_class = match status.health {
+            Some(h) if h < 500 => "ok",
+            Some(_) => "mismatch",
+            None => "unknown",
+        };
+        let head = crate::head_monitor::head(u);
+        let _ = write!(
+            page,
+            "<tr><td>{}</td><td>{}</td><td class=\"{}\">{}</td><td>{}</td><td>{}</td><td>{}</td></tr>",
+            escape(u.as_str()),
+            escape(&status.version.map(|v| v.version).unwrap_or_default()),
+            health_class,
+            status.health.map(|h| h.to_string()).unwrap_or_default(),
+            status.is_syncing(),
+            status.peers.map(|p| p.connected.to_string()).unwrap_or_default(),
+            head.map(|h| h.slot.to_string()).unwrap_or_default(),
+        );
+    }
+    page.push_str("</table>");
+}
+
+fn checks_matrix(page: &mut String, endpoints: &[Url]) {
+    let results: Vec<_> = endpoints.iter().map(crate::history::last_results).collect();
+    let checks: BTreeSet<&String> = results.iter().flat_map(|r| r.keys()).collect();
+    page.push_str("<h2>Checks</h2><table><tr><th>check</th>");
+    for u in endpoints.iter() {
+        let _ = write!(page, "<th>{}</th>", escape(u.as_str()));
+    }
+    page.push_str("</tr>");
+    for check in checks {
+        let _ = write!(page, "<tr><td>{}</td>", escape(check));
+        for r in results.iter() {
+            match r.get(check) {
+                Some(r) if r.matched => {
+                    let _ = write!(
+                        page,
+                        "<td class=\"ok\">ok <small>{}</small></td>",
+                        ago(r.at)
+                    );
+                }
+                Some(r) => {
+                    let _ = write!(
+                        page,
+                        "<td class=\"mismatch\">mismatch <small>{}</small></td>",
+                        ago(r.at)
+                    );
+                }
+                None => page.push_str("<td class=\"unknown\">-</td>"),
+            }
+        }
+        page.push_str("</tr>");
+    }
+    page.push_str("</table>");
+}
+
+fn latencies_matrix(page: &mut String, endpoints: &[Url]) {
+    let mut latencies: BTreeMap<String, BTreeMap<Url, Vec<u64>>> = BTreeMap::new();
+    for ((check, u), samples) in crate::history::latencies() {
+        latencies.entry(check).or_default().insert(u, samples);
+    }
+    page.push_str("<h2>Latencies</h2><table><tr><th>check</th>");
+    for u in endpoints.iter() {
+        let _ = write!(page, "<th>{}</th>", escape(u.as_str()));
+    }
+    page.push_str("</tr>");
+    for (check, by_endpoint) in latencies.iter() {
+        let _ = write!(page, "<tr><td>{}</td>", escape(check));
+        for u in endpoints.iter() {
+            match by_endpoint.get(u) {
+                Some(samples) if !samples.is_empty() => {
+                    let _ = write!(
+                        page,
+                        "<td>{} <small>{}ms</small></td>",
+                        sparkline(samples),
+                        samples.last().unwrap()
+                    );
+                }
+                _ => page.push_str("<td class=\"unknown\">-</td>"),
+            }
+        }
+        page.push_str("</tr>");
+    }
+    page.push_str("</table>");
+}
+
+fn mismatches(page: &mut String) {
+    page.push_str("<h2>Recent mismatches</h2>");
+    let mismatches = crate::history::mismatches();
+    if mismatches.is_empty() {
+        page.push_str("<p>None</p>");
+    }
+    for m in mismatches.iter().take(MAX_MISMATCHES_SHOWN) {
+        let _ = write!(
+            page,
+            "<h3>{} <small>{}</small></h3>",
+            escape(&m.method),
+            ago(m.at)
+        );
+        for (u, diff) in m.diffs.iter() {
+            let _ = write!(
+                page,
+                "<p>{} against {}</p><pre>",
+                escape(u),
+                escape(&m.baseline)
+            );
+            for (tag, line) in diff.iter() {
+                let line = escape(line);
+                let _ = match tag {
+                    '-' => writeln!(page, "<span class=\"removed\">- {}</span>", line),
+                    '+' => writeln!(page, "<span class=\"added\">+ {}</span>", line),
+                    _ => writeln!(page, "  {}", line),
+                };
+            }
+            page.push_str("</pre>");
+        }
+    }
+}
+
+// Renders latencies as an inline SVG polyline scaled to the largest one.
+fn sparkline(samples: &[u64]) -> String {
+    let max = samples.iter().copied().max().unwrap_or_default().max(1) as f64;
+    let step = SPARKLINE_WIDTH as f64 / (samples.len().max(2) - 1) as f64;
+    let points: Vec<String> = samples
+        .iter()
+        .enumerate()
+        .map(|(i, ms)| {
+            let y = SPARKLINE_HEIGHT as f64 * (1.0 - *ms as f64 / max);
+            format!("{:.1},{:.1}", i as f64 * step, y)
+        })
+        .collect();
+    format!(
+        "<svg width=\"{w}\" height=\"{h}\" viewBox=\"0 0 {w} {h}\">\
+         <polyline fill=\"none\" stroke=\"#36c\" stroke-width=\"1\" points=\"{}\"/></svg>",
+        points.join(" "),
+        w = SPARKLINE_WIDTH,
+        h = SPARKLINE_HEIGHT,
+    )
+}
+
+fn ago(at: u64) -> String {
+    format!("{}s ago", crate::incidents::now().saturating_sub(at))
+}
+
+fn escape(s: &str) -> String {
+    s.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
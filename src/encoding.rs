@@ -0,0 +1,231 @@
+use ethereum_consensus::{altair, bellatrix, capella, phase0, primitives::Root};
+use eyre::{eyre, Result};
+use serde::de::DeserializeOwned;
+use ssz_rs::{Deserialize, Merkleized};
+use url::Url;
+
+/// Selects which encodings the SSZ-capable checks request from each node.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum EncodingMode {
+    /// Only the JSON checks run.
+    Json,
+    /// Fetch both encodings from each node, verify they decode to the same
+    /// object and compare nodes over their SSZ bytes.
+    Both,
+    /// Compare nodes purely over their SSZ bytes.
+    Ssz,
+}
+
+/// The size of a blob in bytes.
+const BYTES_PER_BLOB: usize = 131072;
+
+/// The depth of the proof that a sidecar's commitment is in its block body.
+const KZG_COMMITMENT_INCLUSION_PROOF_DEPTH: usize = 17;
+
+/// The size of a blob sidecar in SSZ. Every field of a sidecar is fixed
+/// size, so a list of sidecars encodes as their concatenation: the index,
+/// blob, commitment, proof, signed block header and inclusion proof.
+const BLOB_SIDECAR_LEN: usize = 8
+    + BYTES_PER_BLOB
+    + 48
+    + 48
+    + (8 + 8 + 32 * 3 + 96)
+    + 32 * KZG_COMMITMENT_INCLUSION_PROOF_DEPTH;
+
+/// Objects which beacon nodes serve in both JSON and SSZ encodings.
+#[derive(Debug, Clone, Copy)]
+pub enum Object {
+    Block,
+    State,
+    BlobSidecars,
+}
+
+impl Object {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Object::Block => "block",
+            Object::State => "state",
+            Object::BlobSidecars => "blob_sidecars",
+        }
+    }
+}
+
+/// Decodes the JSON and SSZ encodings of an object served by a node at the
+/// given fork, returning whether they describe the same object, or `None`
+/// for forks we have no ethereum-consensus containers for, such as Deneb.
+/// Those can still be compared across nodes over their SSZ bytes.
+pub fn encodings_agree(
+    object: Object,
+    version: &str,
+    json: serde_json::Value,
+    ssz: &[u8],
+) -> Result<Option<bool>> {
+    let agree = match (object, version) {
+        (Object::Block, "phase0") => agree::<phase0::mainnet::SignedBeaconBlock>(json, ssz),
+        (Object::Block, "altair") => agree::<altair::mainnet::SignedBeaconBlock>(json, ssz),
+        (Object::Block, "bellatrix") => agree::<bellatrix::mainnet::SignedBeaconBlock>(json, ssz),
+        (Object::Block, "capella") => agree::<capella::mainnet::SignedBeaconBlock>(json, ssz),
+        (Object::State, "phase0") => agree::<phase0::mainnet::BeaconState>(json, ssz),
+        (Object::State, "altair") => agree::<altair::mainnet::BeaconState>(json, ssz),
+        (Object::State, "bellatrix") => agree::<bellatrix::mainnet::BeaconState>(json, ssz),
+        (Object::State, "capella") => agree::<capella::mainnet::BeaconState>(json, ssz),
+        (Object::BlobSidecars, _) => blob_sidecars_agree(json, ssz),
+        _ => return Ok(None),
+    };
+    agree.map(Some)
+}
+
+fn agree<T>(json: serde_json::Value, ssz: &[u8]) -> Result<bool>
+where
+    T: DeserializeOwned + Deserialize + PartialEq,
+{
+    let from_json: T = serde_json::from_value(json)?;
+    let from_ssz =
+        <T as Deserialize>::deserialize(ssz).map_err(|e| eyre!("invalid ssz: {:?}", e))?;
+    Ok(from_json == from_ssz)
+}
+
+// Blob sidecars have no container in ethereum-consensus, so their JSON
+// encoding is encoded to SSZ by hand and compared with the node's.
+fn blob_sidecars_agree(json: serde_json::Value, ssz: &[u8]) -> Result<bool> {
+    let sidecars: Vec<serde_json::Value> = serde_json::from_value(json)?;
+    if ssz.len() % BLOB_SIDECAR_LEN != 0 {
+        return Err(eyre!("invalid blob sidecars ssz of {} bytes", ssz.len()));
+    }
+    if ssz.len() / BLOB_SIDECAR_LEN != sidecars.len() {
+        return Ok(false);
+    }
+    for (json, ssz) in sidecars.iter().zip(ssz.chunks(BLOB_SIDECAR_LEN)) {
+        if encode_blob_sidecar(json)? != ssz {
+            return Ok(false);
+        }
+    }
+    Ok(true)
+}
+
+fn encode_blob_sidecar(json: &serde_json::Value) -> Result<Vec<u8>> {
+    let header = &json["signed_block_header"];
+    let mut out = Vec::with_capacity(BLOB_SIDECAR_LEN);
+    out.extend(uint(&json["index"])?);
+    out.extend(bytes(&json["blob"], BYTES_PER_BLOB)?);
+    out.extend(bytes(&json["kzg_commitment"], 48)?);
+    out.extend(bytes(&json["kzg_proof"], 48)?);
+    out.extend(uint(&header["message"]["slot"])?);
+    out.extend(uint(&header["message"]["proposer_index"])?);
+    for field in ["parent_root", "state_root", "body_root"] {
+        out.extend(bytes(&header["message"][field], 32)?);
+    }
+    out.extend(bytes(&header["signature"], 96)?);
+    let proof = json["kzg_commitment_inclusion_proof"]
+        .as_array()
+        .ok_or_else(|| eyre!("missing kzg_commitment_inclusion_proof"))?;
+    if proof.len() != KZG_COMMITMENT_INCLUSION_PROOF_DEPTH {
+        return Err(eyre!("inclusion proof of {} nodes", proof.len()));
+    }
+    for node in proof.iter() {
+        out.extend(bytes(node, 32)?);
+    }
+    Ok(out)
+}
+
+// Encodes a quoted integer as a little endian uint64.
+fn uint(v: &serde_json::Value) -> Result<[u8; 8]> {
+    let n: u64 = v
+        .as_str()
+        .ok_or_else(|| eyre!("expected a quoted integer, got {}", v))?
+        .parse()?;
+    Ok(n.to_le_bytes())
+}
+
+// Decodes 0x prefixed hex of a fixed length.
+fn bytes(v: &serde_json::Value, len: usize) -> Result<Vec<u8>> {
+    let s = v
+        .as_str()
+        .ok_or_else(|| eyre!("expected hex bytes, got {}", v))?;
+    let b = hex::decode(s.trim_start_matches("0x"))?;
+    if b.len() != len {
+        return Err(eyre!("expected {} bytes, got {}", len, b.len()));
+    }
+    Ok(b)
+}
+
+/// Fetches an object from a node in both encodings, returning whether the
+/// encodings agree, if that can be told, along with the SSZ bytes for
+/// comparison across nodes.
+pub async fn fetch_both(object: Object, u: &Url, path: &str) -> Result<(Option<bool>, Vec<u8>)> {
+    let (ssz_version, ssz) = crate::http::get_ssz(u, path).await?;
+    let json: crate::http::VersionedResponse<serde_json::Value> =
+        crate::http::get_raw(u, path).await?;
+    // Encodings which claim different forks can't describe the same object.
+    if let (Some(ssz_version), Some(version)) = (&ssz_version, &json.version) {
+        if ssz_version != version {
+            return Ok((Some(false), ssz));
+        }
+    }
+    let version = json.version.unwrap_or_default();
+    let agree = encodings_agree(object, &version, json.data, &ssz)?;
+    Ok((agree, ssz))
+}
+
+/// A signed block decoded with the container type of the fork the node
+/// reported it at.
+pub enum VersionedBlock {
+    Phase0(Box<phase0::mainnet::SignedBeaconBlock>),
+    Altair(Box<altair::mainnet::SignedBeaconBlock>),
+    Bellatrix(Box<bellatrix::mainnet::SignedBeaconBlock>),
+    Capella(Box<capella::mainnet::SignedBeaconBlock>),
+}
+
+impl VersionedBlock {
+    /// Decodes a block, or returns None for forks we have no
+    /// ethereum-consensus containers for, such as Deneb.
+    pub fn decode(version: &str, ssz: &[u8]) -> Result<Option<Self>> {
+        let invalid = |e| eyre!("invalid {} block ssz: {:?}", version, e);
+        let block = match version {
+            "phase0" => Self::Phase0(Box::new(
+                phase0::mainnet::SignedBeaconBlock::deserialize(ssz).map_err(invalid)?,
+            )),
+            "altair" => Self::Altair(Box::new(
+                altair::mainnet::SignedBeaconBlock::deserialize(ssz).map_err(invalid)?,
+            )),
+            "bellatrix" => Self::Bellatrix(Box::new(
+                bellatrix::mainnet::SignedBeaconBlock::deserialize(ssz).map_err(invalid)?,
+            )),
+            "capella" => Self::Capella(Box::new(
+                capella::mainnet::SignedBeaconBlock::deserialize(ssz).map_err(invalid)?,
+            )),
+            _ => return Ok(None),
+        };
+        Ok(Some(block))
+    }
+
+    pub fn slot(&self) -> u64 {
+        match self {
+            Self::Phase0(b) => b.message.slot,
+            Self::Altair(b) => b.message.slot,
+            Self::Bellatrix(b) => b.message.slot,
+            Self::Capella(b) => b.message.slot,
+        }
+    }
+
+    /// The state root the block commits to.
+    pub fn state_root(&self) -> Root {
+        match self {
+            Self::Phase0(b) => b.message.state_root.clone(),
+            Self::Altair(b) => b.message.state_root.clone(),
+            Self::Bellatrix(b) => b.message.state_root.clone(),
+            Self::Capella(b) => b.message.state_root.clone(),
+        }
+    }
+
+    /// Computes the block root, i.e. the hash tree root of the unsigned block.
+    pub fn root(&mut self) -> Result<Root> {
+        let root = match self {
+            Self::Phase0(b) => b.message.hash_tree_root(),
+            Self::Altair(b) => b.message.hash_tree_root(),
+            Self::Bellatrix(b) => b.message.hash_tree_root(),
+            Self::Capella(b) => b.message.hash_tree_root(),
+        };
+        root.map_err(|e| eyre!("failed to merkleize block: {:?}", e))
+    }
+}
@@ -1,39 +1,49 @@
+use crate::encoding::{Object, VersionedBlock};
+use crate::state_diff::VersionedState;
 use crate::types::*;
-use beacon_api_client::{BlockId, Client, PublicKeyOrIndex, StateId, ValidatorStatus};
+use beacon_api_client::{BlockId, PublicKeyOrIndex, StateId, ValidatorStatus};
 use ethereum_consensus::{
     clock,
-    phase0::mainnet::SignedBeaconBlock,
-    primitives::{Root, ValidatorIndex},
+    phase0::mainnet::{Attestation, SignedBeaconBlock},
+    primitives::{Epoch, Root, ValidatorIndex},
 };
 use eyre::Result;
 use human_duration::human_duration;
 use rand::{rngs::ThreadRng, seq::SliceRandom, Rng};
+use std::collections::HashSet;
 use std::time::Duration;
 use tokio::time::Instant;
 use tracing::{info, warn};
 use url::Url;
 
+const SLOTS_PER_EPOCH: u64 = 32;
+const EPOCHS_PER_SYNC_COMMITTEE_PERIOD: u64 = 256;
+const MAX_BLOBS_PER_BLOCK: u64 = 6;
+
 pub async fn check_state_root(urls: Vec<Url>) -> Result<()> {
     let mut responses: Vec<Root> = vec![];
+    let mut responders: Vec<Url> = vec![];
     let mut latencies = vec![];
 
     let id = random_state_id();
     let method = format!("/eth/v1/beacon/states/{}/root", id.inner);
     let mut succeeded = 0;
     for u in urls.iter() {
-        // TODO: Share the clients instead.
-        let client = Client::new(u.clone());
+        let client = crate::http::api_client(u);
         let start = Instant::now();
         info!("Calling {} endpoint={}", method, u);
         let data = match client.get_state_root(id.clone().inner).await {
             Ok(res) => res,
             Err(e) => {
                 warn!("Request method={} endpoint={} failed {:?}", method, u, e);
+                crate::slo::observe_failure("get_state_root", u);
                 continue;
             }
         };
+        crate::slo::observe("get_state_root", &method, u, start.elapsed());
         latencies.push(start.elapsed().as_millis() as u64);
         responses.push(data);
+        responders.push(u.clone());
         succeeded += 1;
     }
     let median_latency = Duration::from_millis(median(&mut latencies));
@@ -42,37 +52,631 @@ pub async fn check_state_root(urls: Vec<Url>) -> Result<()> {
         method,
         human_duration(&median_latency),
     );
-    crate::metrics::GET_STATE_ROOT_LATENCY_MILLISECONDS.observe(median_latency.as_millis() as f64);
+    crate::metrics::update(|| {
+        crate::metrics::GET_STATE_ROOT_LATENCY_MILLISECONDS
+            .observe(median_latency.as_millis() as f64)
+    });
+
+    if mismatched_responses(&method, &responders, &responses, succeeded) {
+        crate::metrics::update(|| crate::metrics::STATE_ROOT_NOT_EQUAL_TOTAL.inc());
+        warn!("MISMATCHED REQUEST: endpoint={}", method);
+        warn!("Responses: {:?}", responses);
+        // A root alone doesn't tell us why the states differ, so fetch
+        // the full states and diff them.
+        diff_debug_states(&id.inner.to_string(), &responders).await;
+    }
+    Ok(())
+}
+
+pub async fn check_block_hash_tree_root(urls: Vec<Url>) -> Result<()> {
+    let mut responses: Vec<Root> = vec![];
+    let mut responders: Vec<Url> = vec![];
+    let mut succeeded = 0;
+
+    let id = random_settled_id();
+    let method = format!("/eth/v2/beacon/blocks/{}", id);
+    for u in urls.iter() {
+        info!("Calling {} endpoint={}", method, u);
+        let block = match crate::http::get_ssz(u, &method[1..]).await {
+            Ok((Some(version), ssz)) => VersionedBlock::decode(&version, &ssz),
+            Ok((None, _)) => Err(eyre::eyre!(
+                "missing {} header",
+                crate::http::CONSENSUS_VERSION_HEADER
+            )),
+            Err(e) => Err(e.into()),
+        };
+        let block = block.and_then(|b| b.map(|mut b| b.root().map(|root| (b, root))).transpose());
+        let (block, root) = match block {
+            Ok(Some(res)) => res,
+            Ok(None) => {
+                info!(
+                    "Skipping computed roots of unsupported version method={} endpoint={}",
+                    method, u
+                );
+                continue;
+            }
+            Err(e) => {
+                warn!("Request method={} endpoint={} failed {:?}", method, u, e);
+                continue;
+            }
+        };
+
+        let path = format!("eth/v1/beacon/blocks/{}/root", id);
+        verify_root(u, "block", "block_root", &path, &root).await;
+        let path = format!("eth/v1/beacon/headers/{}", id);
+        verify_root(u, "block", "header_root", &path, &root).await;
+        // The state at the block's slot is the post-state of the block,
+        // so its root must be the one the block commits to.
+        let path = format!("eth/v1/beacon/states/{}/root", block.slot());
+        verify_root(u, "block", "state_root", &path, &block.state_root()).await;
+
+        responses.push(root);
+        responders.push(u.clone());
+        succeeded += 1;
+    }
+
+    if mismatched_responses(&method, &responders, &responses, succeeded) {
+        crate::metrics::update(|| {
+            crate::metrics::COMPUTED_ROOT_NOT_EQUAL_TOTAL
+                .with_label_values(&["block"])
+                .inc()
+        });
+        warn!("MISMATCHED COMPUTED ROOTS: endpoint={}", method);
+        warn!("Responses: {:?}", responses);
+    }
+    Ok(())
+}
+
+pub async fn check_state_hash_tree_root(urls: Vec<Url>) -> Result<()> {
+    let mut responses: Vec<Root> = vec![];
+    let mut responders: Vec<Url> = vec![];
+    let mut succeeded = 0;
+
+    let id = random_settled_id();
+    let method = format!("/eth/v2/debug/beacon/states/{}", id);
+    for u in urls.iter() {
+        info!("Calling {} endpoint={}", method, u);
+        let state = match crate::http::get_ssz(u, &method[1..]).await {
+            Ok((Some(version), ssz)) => VersionedState::decode(&version, &ssz),
+            Ok((None, _)) => Err(eyre::eyre!(
+                "missing {} header",
+                crate::http::CONSENSUS_VERSION_HEADER
+            )),
+            Err(e) => Err(e.into()),
+        };
+        let root = match state.and_then(|s| s.map(|mut s| s.root()).transpose()) {
+            Ok(Some(res)) => res,
+            Ok(None) => {
+                info!(
+                    "Skipping computed roots of unsupported version method={} endpoint={}",
+                    method, u
+                );
+                continue;
+            }
+            Err(e) => {
+                warn!("Request method={} endpoint={} failed {:?}", method, u, e);
+                continue;
+            }
+        };
+
+        let path = format!("eth/v1/beacon/states/{}/root", id);
+        verify_root(u, "state", "state_root", &path, &root).await;
+
+        responses.push(root);
+        responders.push(u.clone());
+        succeeded += 1;
+    }
+
+    if mismatched_responses(&method, &responders, &responses, succeeded) {
+        crate::metrics::update(|| {
+            crate::metrics::COMPUTED_ROOT_NOT_EQUAL_TOTAL
+                .with_label_values(&["state"])
+                .inc()
+        });
+        warn!("MISMATCHED COMPUTED ROOTS: endpoint={}", method);
+        warn!("Responses: {:?}", responses);
+    }
+    Ok(())
+}
+
+// Compares a root computed locally from an object served by an endpoint
+// with the root the same endpoint reports for it on another route.
+async fn verify_root(u: &Url, object: &str, source: &str, path: &str, computed: &Root) {
+    let reported: RootData = match crate::http::get(u, path).await {
+        Ok(res) => res,
+        Err(e) => {
+            warn!("Request method=/{} endpoint={} failed {:?}", path, u, e);
+            return;
+        }
+    };
+    if reported.root != *computed {
+        crate::metrics::update(|| {
+            crate::metrics::SELF_INCONSISTENT_TOTAL
+                .with_label_values(&[object, source, u.as_str()])
+                .inc()
+        });
+        warn!(
+            "SELF-INCONSISTENT NODE: endpoint={} method=/{} reported={:?} computed={:?}",
+            u, path, reported.root, computed,
+        );
+    }
+}
+
+pub async fn check_metadata(urls: Vec<Url>) -> Result<()> {
+    let id = random_state_id();
+    let mut routes = vec![
+        "/eth/v1/beacon/states/{id}/root",
+        "/eth/v1/beacon/states/{id}/finality_checkpoints",
+    ];
+    // Block routes do not accept the justified id.
+    if !matches!(id.inner, StateId::Justified) {
+        routes.push("/eth/v2/beacon/blocks/{id}");
+        routes.push("/eth/v1/beacon/headers/{id}");
+    }
+    for route in routes {
+        check_route_metadata(route, &id, &urls).await;
+    }
+    Ok(())
+}
+
+// Compares the metadata of a route across endpoints and validates that each
+// endpoint's metadata is consistent with the request and with itself.
+async fn check_route_metadata(route: &str, id: &StateIdExt, urls: &[Url]) {
+    let mut responses: Vec<crate::http::Metadata> = vec![];
+    let mut responders: Vec<Url> = vec![];
+    let mut succeeded = 0;
+
+    let method = route.replace("{id}", &id.inner.to_string());
+    for u in urls.iter() {
+        info!("Calling {} endpoint={}", method, u);
+        let metadata = match crate::http::get_metadata(u, &method[1..]).await {
+            Ok(res) => res,
+            Err(e) => {
+                warn!("Request method={} endpoint={} failed {:?}", method, u, e);
+                continue;
+            }
+        };
+        for reason in inconsistent_metadata(route, id, &metadata) {
+            crate::metrics::update(|| {
+                crate::metrics::METADATA_INCONSISTENT_TOTAL
+                    .with_label_values(&[route, reason, u.as_str()])
+                    .inc()
+            });
+            warn!(
+                "INCONSISTENT METADATA: endpoint={} method={} reason={} metadata={:?}",
+                u, method, reason, metadata,
+            );
+        }
+        responses.push(metadata);
+        responders.push(u.clone());
+        succeeded += 1;
+    }
+
+    if mismatched_responses(&method, &responders, &responses, succeeded) {
+        crate::metrics::update(|| {
+            crate::metrics::METADATA_NOT_EQUAL_TOTAL
+                .with_label_values(&[route])
+                .inc()
+        });
+        warn!("MISMATCHED METADATA: endpoint={}", method);
+        warn!("Responses: {:?}", responses);
+    }
+}
+
+// Returns the reasons an endpoint's metadata contradicts the request or itself.
+fn inconsistent_metadata(
+    route: &str,
+    id: &StateIdExt,
+    metadata: &crate::http::Metadata,
+) -> Vec<&'static str> {
+    let mut reasons = vec![];
+    if metadata.execution_optimistic.is_none() {
+        reasons.push("missing_execution_optimistic");
+    }
+    if matches!(id.inner, StateId::Finalized) && metadata.finalized == Some(false) {
+        reasons.push("finalized_id_not_finalized");
+    }
+    if metadata.finalized == Some(true) && metadata.execution_optimistic == Some(true) {
+        reasons.push("finalized_and_optimistic");
+    }
+    if route.starts_with("/eth/v2/") {
+        match (&metadata.version, &metadata.version_header) {
+            (None, _) => reasons.push("missing_version"),
+            (Some(v), Some(h)) if v != h => reasons.push("version_header_mismatch"),
+            _ => {}
+        }
+    }
+    reasons
+}
+
+/// A deliberately invalid request and the statuses the beacon API spec
+/// allows nodes to answer it with.
+struct ErrorCase {
+    name: &'static str,
+    path: String,
+    body: Option<serde_json::Value>,
+    expected: &'static [u16],
+}
+
+fn error_cases() -> Vec<ErrorCase> {
+    let c = clock::for_goerli();
+    let future_slot = c.current_slot() + 1_000_000;
+    let future_epoch = future_slot / SLOTS_PER_EPOCH;
+    let unknown_root = format!("0x{}", "ab".repeat(32));
+    let oversized_ids: Vec<String> = (0..10_000).map(|i| i.to_string()).collect();
+    vec![
+        ErrorCase {
+            name: "malformed_state_id",
+            path: "eth/v1/beacon/states/not_a_state/root".to_string(),
+            body: None,
+            expected: &[400],
+        },
+        ErrorCase {
+            name: "malformed_block_id",
+            path: "eth/v2/beacon/blocks/not_a_block".to_string(),
+            body: None,
+            expected: &[400],
+        },
+        ErrorCase {
+            name: "future_slot_state",
+            path: format!("eth/v1/beacon/states/{}/root", future_slot),
+            body: None,
+            expected: &[404],
+        },
+        ErrorCase {
+            name: "future_slot_block",
+            path: format!("eth/v2/beacon/blocks/{}", future_slot),
+            body: None,
+            expected: &[404],
+        },
+        ErrorCase {
+            name: "unknown_state_root",
+            path: format!("eth/v1/beacon/states/{}/root", unknown_root),
+            body: None,
+            expected: &[404],
+        },
+        ErrorCase {
+            name: "unknown_block_root",
+            path: format!("eth/v1/beacon/blocks/{}/root", unknown_root),
+            body: None,
+            expected: &[404],
+        },
+        ErrorCase {
+            name: "out_of_range_validator_index",
+            path: "eth/v1/beacon/states/head/validators/999999999".to_string(),
+            body: None,
+            expected: &[404],
+        },
+        ErrorCase {
+            name: "bad_pubkey",
+            path: "eth/v1/beacon/states/head/validators/0x1234".to_string(),
+            body: None,
+            expected: &[400],
+        },
+        ErrorCase {
+            name: "oversized_id_list",
+            path: format!(
+                "eth/v1/beacon/states/head/validators?id={}",
+                oversized_ids.join(",")
+            ),
+            body: None,
+            expected: &[400, 414],
+        },
+        ErrorCase {
+            name: "future_epoch_attester_duties",
+            path: format!("eth/v1/validator/duties/attester/{}", future_epoch),
+            body: Some(serde_json::json!(["1"])),
+            expected: &[400],
+        },
+    ]
+}
+
+pub async fn check_error_paths(urls: Vec<Url>) -> Result<()> {
+    for case in error_cases() {
+        check_error_case(&case, &urls).await;
+    }
+    Ok(())
+}
+
+// Sends an invalid request to every endpoint, verifying each answers with an
+// allowed status and a `{code, message}` body, and that they agree on the status.
+async fn check_error_case(case: &ErrorCase, urls: &[Url]) {
+    let mut responses: Vec<u16> = vec![];
+    let mut responders: Vec<Url> = vec![];
+    let mut succeeded = 0;
+
+    let method = format!("/{}", case.path);
+    for u in urls.iter() {
+        info!("Calling error case {} endpoint={}", case.name, u);
+        let (status, body) = match crate::http::send(u, &case.path, case.body.as_ref()).await {
+            Ok(res) => res,
+            Err(e) => {
+                warn!("Request method={} endpoint={} failed {:?}", method, u, e);
+                continue;
+            }
+        };
+        let status = status.as_u16();
+        for reason in nonconformant_error(case, status, &body) {
+            crate::metrics::update(|| {
+                crate::metrics::ERROR_PATH_NONCONFORMANT_TOTAL
+                    .with_label_values(&[case.name, reason, u.as_str()])
+                    .inc()
+            });
+            warn!(
+                "NONCONFORMANT ERROR: endpoint={} case={} reason={} status={} expected={:?} body={}",
+                u, case.name, reason, status, case.expected, body,
+            );
+        }
+        responses.push(status);
+        responders.push(u.clone());
+        succeeded += 1;
+    }
+
+    if mismatched_responses(case.name, &responders, &responses, succeeded) {
+        crate::metrics::update(|| {
+            crate::metrics::ERROR_PATH_NOT_EQUAL_TOTAL
+                .with_label_values(&[case.name])
+                .inc()
+        });
+        warn!("MISMATCHED ERROR STATUSES: case={}", case.name);
+        warn!("Responses: {:?}", responses);
+    }
+}
+
+// Returns the ways an endpoint's answer to an invalid request deviates from the spec.
+fn nonconformant_error(case: &ErrorCase, status: u16, body: &str) -> Vec<&'static str> {
+    let mut reasons = vec![];
+    if status >= 500 {
+        reasons.push("server_error");
+    } else if !case.expected.contains(&status) {
+        reasons.push("unexpected_status");
+    }
+    let body: Option<serde_json::Value> = serde_json::from_str(body).ok();
+    let shaped = body
+        .map(|b| b["code"].as_u64() == Some(status as u64) && b["message"].is_string())
+        .unwrap_or(false);
+    if !shaped {
+        reasons.push("malformed_error_body");
+    }
+    reasons
+}
+
+// Requests the routes which are otherwise only fetched through beacon_api_client
+// as raw JSON, so their responses are validated against the OpenAPI spec.
+pub async fn check_response_schemas(urls: Vec<Url>) -> Result<()> {
+    let id = random_state_id();
+    let indices: Vec<String> = random_indices().iter().map(|i| i.to_string()).collect();
+    let epoch = random_duties_epoch();
+    let mut paths = vec![
+        format!("eth/v1/beacon/states/{}/root", id.inner),
+        format!("eth/v1/beacon/states/{}/finality_checkpoints", id.inner),
+        format!(
+            "eth/v1/beacon/states/{}/validators?id={}",
+            id.inner,
+            indices.join(",")
+        ),
+        format!(
+            "eth/v1/beacon/states/{}/validator_balances?id={}",
+            id.inner,
+            indices.join(",")
+        ),
+        format!("eth/v1/validator/duties/proposer/{}", epoch),
+    ];
+    // Block routes do not accept the justified id.
+    if !matches!(id.inner, StateId::Justified) {
+        paths.push(format!("eth/v2/beacon/blocks/{}", id.inner));
+    }
+    for path in paths.iter() {
+        for u in urls.iter() {
+            info!("Calling /{} endpoint={}", path, u);
+            if let Err(e) = crate::http::get_raw::<serde_json::Value>(u, path).await {
+                warn!("Request method=/{} endpoint={} failed {:?}", path, u, e);
+            }
+        }
+    }
+    Ok(())
+}
+
+// Requests every GET operation of the OpenAPI spec we can synthesize parameters
+// for from all endpoints and compares their raw JSON responses.
+pub async fn check_openapi_routes(urls: Vec<Url>) -> Result<()> {
+    let spec = match crate::openapi::spec() {
+        Some(spec) => spec,
+        None => return Ok(()),
+    };
+    let ctx = match chain_context(&urls).await {
+        Some(ctx) => ctx,
+        None => return Ok(()),
+    };
+    for (template, path) in spec.synthesize_gets(&ctx) {
+        check_openapi_route(&template, &path, &urls).await;
+    }
+    Ok(())
+}
+
+// Builds the parameters for synthesized requests from the chain as seen by
+// the first endpoint which can answer. Ids relative to the head are avoided
+// so nodes answering at slightly different times still agree.
+async fn chain_context(urls: &[Url]) -> Option<crate::openapi::ChainContext> {
+    let path = "eth/v1/beacon/states/finalized/finality_checkpoints";
+    let mut block_root = None;
+    for u in urls.iter() {
+        match crate::http::get::<serde_json::Value>(u, path).await {
+            Ok(res) => {
+                block_root = res["finalized"]["root"].as_str().map(|r| r.to_string());
+                break;
+            }
+            Err(e) => {
+                warn!("Request method=/{} endpoint={} failed {:?}", path, u, e);
+                continue;
+            }
+        }
+    }
+    let c = clock::for_goerli();
+    let mut rng = rand::thread_rng();
+    let slot = random_slot(&mut rng);
+    let epoch = slot / SLOTS_PER_EPOCH;
+    Some(crate::openapi::ChainContext {
+        state_id: random_settled_id(),
+        block_id: random_settled_id(),
+        block_root: block_root?,
+        slot,
+        epoch,
+        sync_committee_period: c.current_slot()
+            / SLOTS_PER_EPOCH
+            / EPOCHS_PER_SYNC_COMMITTEE_PERIOD,
+        validator_indices: random_indices().iter().map(|i| *i as u64).collect(),
+    })
+}
+
+async fn check_openapi_route(template: &str, path: &str, urls: &[Url]) {
+    let mut responses: Vec<serde_json::Value> = vec![];
+    let mut responders: Vec<Url> = vec![];
+    let mut succeeded = 0;
+
+    let method = format!("/{}", path);
+    for u in urls.iter() {
+        let start = Instant::now();
+        info!("Calling {} endpoint={}", method, u);
+        let res: serde_json::Value = match crate::http::get_raw(u, path).await {
+            Ok(res) => res,
+            Err(e) if e.is_unsupported() => {
+                info!("Unsupported method={} endpoint={}", method, u);
+                crate::metrics::update(|| {
+                    crate::metrics::OPENAPI_UNSUPPORTED_TOTAL
+                        .with_label_values(&[template, u.as_str()])
+                        .inc()
+                });
+                continue;
+            }
+            Err(e) => {
+                warn!("Request method={} endpoint={} failed {:?}", method, u, e);
+                continue;
+            }
+        };
+        crate::metrics::update(|| {
+            crate::metrics::GET_OPENAPI_LATENCY_MILLISECONDS
+                .with_label_values(&[template])
+                .observe(start.elapsed().as_millis() as f64)
+        });
+        responses.push(res);
+        responders.push(u.clone());
+        succeeded += 1;
+    }
 
-    if mismatched_responses(&method, &urls, &responses, succeeded) {
-        crate::metrics::STATE_ROOT_NOT_EQUAL_TOTAL.inc();
+    if mismatched_responses(&method, &responders, &responses, succeeded) {
+        crate::metrics::update(|| {
+            crate::metrics::OPENAPI_NOT_EQUAL_TOTAL
+                .with_label_values(&[template])
+                .inc()
+        });
         warn!("MISMATCHED REQUEST: endpoint={}", method);
         warn!("Responses: {:?}", responses);
     }
+}
+
+pub async fn check_debug_state(urls: Vec<Url>) -> Result<()> {
+    let id = random_settled_id();
+    diff_debug_states(&id, &urls).await;
     Ok(())
 }
 
+// Downloads the SSZ state for an id from each endpoint and reports which
+// fields of the state differ between them.
+async fn diff_debug_states(id: &str, urls: &[Url]) {
+    let mut responses: Vec<VersionedState> = vec![];
+    let mut responders: Vec<Url> = vec![];
+    let mut latencies = vec![];
+    let mut succeeded = 0;
+
+    let method = format!("/eth/v2/debug/beacon/states/{}", id);
+    for u in urls.iter() {
+        let start = Instant::now();
+        info!("Calling {} endpoint={}", method, u);
+        let (version, ssz) = match crate::http::get_ssz(u, &method[1..]).await {
+            Ok(res) => res,
+            Err(e) => {
+                warn!("Request method={} endpoint={} failed {:?}", method, u, e);
+                crate::slo::observe_failure("get_debug_state", u);
+                continue;
+            }
+        };
+        // The node answered, so its latency counts whether or not we can
+        // decode what it sent.
+        crate::slo::observe("get_debug_state", &method, u, start.elapsed());
+        latencies.push(start.elapsed().as_millis() as u64);
+        let version = match version {
+            Some(version) => version,
+            None => {
+                warn!(
+                    "Request method={} endpoint={} is missing the {} header",
+                    method,
+                    u,
+                    crate::http::CONSENSUS_VERSION_HEADER
+                );
+                continue;
+            }
+        };
+        let state = match VersionedState::decode(&version, &ssz) {
+            Ok(Some(state)) => state,
+            Ok(None) => {
+                info!(
+                    "Skipping state diff of unsupported version={} method={} endpoint={}",
+                    version, method, u
+                );
+                continue;
+            }
+            Err(e) => {
+                warn!("Decoding method={} endpoint={} failed {:?}", method, u, e);
+                continue;
+            }
+        };
+        responses.push(state);
+        responders.push(u.clone());
+        succeeded += 1;
+    }
+    let median_latency = Duration::from_millis(median(&mut latencies));
+    info!(
+        "{} median_response_time={}",
+        method,
+        human_duration(&median_latency),
+    );
+    crate::metrics::update(|| {
+        crate::metrics::GET_DEBUG_STATE_LATENCY_MILLISECONDS
+            .observe(median_latency.as_millis() as f64)
+    });
+
+    if mismatched_responses(&method, &responders, &responses, succeeded) {
+        crate::metrics::update(|| crate::metrics::DEBUG_STATE_NOT_EQUAL_TOTAL.inc());
+        warn!("MISMATCHED REQUEST: endpoint={}", method);
+        report_field_mismatches(&method, &responders, &responses);
+    }
+}
+
 pub async fn check_finality_checkpoints(urls: Vec<Url>) -> Result<()> {
     let mut responses: Vec<FinalityCheckpointsExt> = vec![];
+    let mut responders: Vec<Url> = vec![];
     let mut latencies = vec![];
 
     let id = random_state_id();
     let method = format!("/eth/v1/beacon/states/{}/finality_checkpoints", id.inner);
     let mut succeeded = 0;
     for u in urls.iter() {
-        // TODO: Share the clients instead.
-        let client = Client::new(u.clone());
+        let client = crate::http::api_client(u);
         let start = Instant::now();
         info!("Calling {} endpoint={}", method, u);
         let cpts = match client.get_finality_checkpoints(id.clone().inner).await {
             Ok(res) => res,
             Err(e) => {
                 warn!("Request method={} endpoint={} failed {:?}", method, u, e);
+                crate::slo::observe_failure("get_finality_checkpoints", u);
                 continue;
             }
         };
+        crate::slo::observe("get_finality_checkpoints", &method, u, start.elapsed());
         latencies.push(start.elapsed().as_millis() as u64);
         responses.push(FinalityCheckpointsExt { inner: cpts });
+        responders.push(u.clone());
         succeeded += 1;
     }
     let median_latency = Duration::from_millis(median(&mut latencies));
@@ -81,11 +685,13 @@ pub async fn check_finality_checkpoints(urls: Vec<Url>) -> Result<()> {
         method,
         human_duration(&median_latency),
     );
-    crate::metrics::GET_FINALITY_CHECKPOINTS_LATENCY_MILLISECONDS
-        .observe(median_latency.as_millis() as f64);
+    crate::metrics::update(|| {
+        crate::metrics::GET_FINALITY_CHECKPOINTS_LATENCY_MILLISECONDS
+            .observe(median_latency.as_millis() as f64)
+    });
 
-    if mismatched_responses(&method, &urls, &responses, succeeded) {
-        crate::metrics::CHECKPOINT_NOT_EQUAL_TOTAL.inc();
+    if mismatched_responses(&method, &responders, &responses, succeeded) {
+        crate::metrics::update(|| crate::metrics::CHECKPOINT_NOT_EQUAL_TOTAL.inc());
         warn!("MISMATCHED REQUEST: endpoint={}", method);
         warn!("Responses: {:?}", responses);
     }
@@ -94,25 +700,86 @@ pub async fn check_finality_checkpoints(urls: Vec<Url>) -> Result<()> {
 
 pub async fn check_block(urls: Vec<Url>) -> Result<()> {
     let mut responses: Vec<SignedBeaconBlock> = vec![];
+    let mut responders: Vec<Url> = vec![];
     let mut latencies = vec![];
     let mut succeeded = 0;
 
     let id = random_block_id();
     let method = format!("/eth/v2/beacon/{}/block", id.inner);
     for u in urls.iter() {
-        // TODO: Share the clients instead.
-        let client = Client::new(u.clone());
+        let client = crate::http::api_client(u);
         let start = Instant::now();
         info!("Calling {} endpoint={}", method, u);
         let block = match client.get_beacon_block(id.clone().inner).await {
             Ok(res) => res,
             Err(e) => {
                 warn!("Request method={} endpoint={} failed {:?}", method, u, e);
+                crate::slo::observe_failure("get_block", u);
                 continue;
             }
         };
+        crate::slo::observe("get_block", &method, u, start.elapsed());
         latencies.push(start.elapsed().as_millis() as u64);
         responses.push(block);
+        responders.push(u.clone());
+        succeeded += 1;
+    }
+    let median_latency = Duration::from_millis(median(&mut latencies));
+    info!(
+        "{} median_response_time={}",
+        method,
+        human_duration(&median_latency),
+    );
+    crate::metrics::update(|| {
+        crate::metrics::GET_BLOCK_LATENCY_MILLISECONDS.observe(median_latency.as_millis() as f64)
+    });
+
+    if mismatched_responses(&method, &responders, &responses, succeeded) {
+        crate::metrics::update(|| crate::metrics::BLOCK_NOT_EQUAL_TOTAL.inc());
+        warn!("MISMATCHED REQUEST: endpoint={}", method);
+        // Nodes can legitimately disagree on the head block while they catch up
+        // or sit on different forks, so we explain those mismatches.
+        if matches!(id.inner, BlockId::Head) {
+            let divergence = crate::head_monitor::classify(&urls);
+            crate::metrics::update(|| {
+                crate::metrics::HEAD_BLOCK_NOT_EQUAL_TOTAL
+                    .with_label_values(&[&divergence.to_string()])
+                    .inc()
+            });
+            warn!("Head divergence across endpoints: {}", divergence);
+        }
+        warn!("Responses: {:?}", responses);
+    }
+    Ok(())
+}
+
+pub async fn check_block_attestations(urls: Vec<Url>) -> Result<()> {
+    let mut responses: Vec<Vec<Attestation>> = vec![];
+    let mut responders: Vec<Url> = vec![];
+    let mut latencies = vec![];
+    let mut succeeded = 0;
+
+    let id = random_block_id();
+    let method = format!("/eth/v1/beacon/blocks/{}/attestations", id.inner);
+    for u in urls.iter() {
+        let client = crate::http::api_client(u);
+        let start = Instant::now();
+        info!("Calling {} endpoint={}", method, u);
+        let attestations = match client
+            .get_attestations_from_beacon_block(id.clone().inner)
+            .await
+        {
+            Ok(res) => res,
+            Err(e) => {
+                warn!("Request method={} endpoint={} failed {:?}", method, u, e);
+                crate::slo::observe_failure("get_block_attestations", u);
+                continue;
+            }
+        };
+        crate::slo::observe("get_block_attestations", &method, u, start.elapsed());
+        latencies.push(start.elapsed().as_millis() as u64);
+        responses.push(attestations);
+        responders.push(u.clone());
         succeeded += 1;
     }
     let median_latency = Duration::from_millis(median(&mut latencies));
@@ -121,10 +788,13 @@ pub async fn check_block(urls: Vec<Url>) -> Result<()> {
         method,
         human_duration(&median_latency),
     );
-    crate::metrics::GET_BLOCK_LATENCY_MILLISECONDS.observe(median_latency.as_millis() as f64);
+    crate::metrics::update(|| {
+        crate::metrics::GET_BLOCK_ATTESTATIONS_LATENCY_MILLISECONDS
+            .observe(median_latency.as_millis() as f64)
+    });
 
-    if mismatched_responses(&method, &urls, &responses, succeeded) {
-        crate::metrics::BLOCK_NOT_EQUAL_TOTAL.inc();
+    if mismatched_responses(&method, &responders, &responses, succeeded) {
+        crate::metrics::update(|| crate::metrics::BLOCK_ATTESTATIONS_NOT_EQUAL_TOTAL.inc());
         warn!("MISMATCHED REQUEST: endpoint={}", method);
         warn!("Responses: {:?}", responses);
     }
@@ -133,6 +803,7 @@ pub async fn check_block(urls: Vec<Url>) -> Result<()> {
 
 pub async fn check_validators(urls: Vec<Url>) -> Result<()> {
     let mut responses: Vec<Vec<ValidatorSummaryExt>> = vec![];
+    let mut responders: Vec<Url> = vec![];
     let mut latencies = vec![];
     let mut succeeded = 0;
 
@@ -142,8 +813,7 @@ pub async fn check_validators(urls: Vec<Url>) -> Result<()> {
     let filters: Vec<ValidatorStatus> = vec![];
 
     for u in urls.iter() {
-        // TODO: Share the clients instead.
-        let client = Client::new(u.clone());
+        let client = crate::http::api_client(u);
 
         let start = Instant::now();
         info!(
@@ -159,9 +829,11 @@ pub async fn check_validators(urls: Vec<Url>) -> Result<()> {
             Ok(res) => res,
             Err(e) => {
                 warn!("Request method={} endpoint={} failed {:?}", method, u, e);
+                crate::slo::observe_failure("get_validators", u);
                 continue;
             }
         };
+        crate::slo::observe("get_validators", &method, u, start.elapsed());
         latencies.push(start.elapsed().as_millis() as u64);
 
         // Sort by validator index.
@@ -171,6 +843,7 @@ pub async fn check_validators(urls: Vec<Url>) -> Result<()> {
             .map(|v| ValidatorSummaryExt { inner: v })
             .collect();
         responses.push(ext);
+        responders.push(u.clone());
         succeeded += 1;
     }
     let median_latency = Duration::from_millis(median(&mut latencies));
@@ -179,10 +852,13 @@ pub async fn check_validators(urls: Vec<Url>) -> Result<()> {
         method,
         human_duration(&median_latency),
     );
-    crate::metrics::GET_VALIDATORS_LATENCY_MILLISECONDS.observe(median_latency.as_millis() as f64);
+    crate::metrics::update(|| {
+        crate::metrics::GET_VALIDATORS_LATENCY_MILLISECONDS
+            .observe(median_latency.as_millis() as f64)
+    });
 
-    if mismatched_responses(&method, &urls, &responses, succeeded) {
-        crate::metrics::VALIDATORS_NOT_EQUAL_TOTAL.inc();
+    if mismatched_responses(&method, &responders, &responses, succeeded) {
+        crate::metrics::update(|| crate::metrics::VALIDATORS_NOT_EQUAL_TOTAL.inc());
         warn!(
             "MISMATCHED REQUEST: endpoint={}, indices={:?}",
             method, indices
@@ -194,6 +870,7 @@ pub async fn check_validators(urls: Vec<Url>) -> Result<()> {
 
 pub async fn check_balances(urls: Vec<Url>) -> Result<()> {
     let mut responses: Vec<Vec<BalanceSummaryExt>> = vec![];
+    let mut responders: Vec<Url> = vec![];
     let mut latencies = vec![];
     let mut succeeded = 0;
 
@@ -201,8 +878,7 @@ pub async fn check_balances(urls: Vec<Url>) -> Result<()> {
     let id = random_state_id();
     let method = format!("/eth/v1/beacon/{}/balances", id.inner);
     for u in urls.iter() {
-        // TODO: Share the clients instead.
-        let client = Client::new(u.clone());
+        let client = crate::http::api_client(u);
         let start = Instant::now();
         info!(
             "Calling {} endpoint={}, num_indices={}",
@@ -214,9 +890,11 @@ pub async fn check_balances(urls: Vec<Url>) -> Result<()> {
             Ok(res) => res,
             Err(e) => {
                 warn!("Request method={} endpoint={} failed {:?}", method, u, e);
+                crate::slo::observe_failure("get_balances", u);
                 continue;
             }
         };
+        crate::slo::observe("get_balances", &method, u, start.elapsed());
         latencies.push(start.elapsed().as_millis() as u64);
         // Sort by validator index.
         balances.sort_by(|a, b| a.index.cmp(&b.index));
@@ -225,6 +903,7 @@ pub async fn check_balances(urls: Vec<Url>) -> Result<()> {
             .map(|v| BalanceSummaryExt { inner: v })
             .collect();
         responses.push(ext);
+        responders.push(u.clone());
         succeeded += 1;
     }
     let median_latency = Duration::from_millis(median(&mut latencies));
@@ -234,10 +913,12 @@ pub async fn check_balances(urls: Vec<Url>) -> Result<()> {
         human_duration(&median_latency),
         indices.len(),
     );
-    crate::metrics::GET_BALANCES_LATENCY_MILLISECONDS.observe(median_latency.as_millis() as f64);
+    crate::metrics::update(|| {
+        crate::metrics::GET_BALANCES_LATENCY_MILLISECONDS.observe(median_latency.as_millis() as f64)
+    });
 
-    if mismatched_responses(&method, &urls, &responses, succeeded) {
-        crate::metrics::BALANCES_NOT_EQUAL_TOTAL.inc();
+    if mismatched_responses(&method, &responders, &responses, succeeded) {
+        crate::metrics::update(|| crate::metrics::BALANCES_NOT_EQUAL_TOTAL.inc());
         warn!(
             "MISMATCHED REQUEST: endpoint={}, indices={:?}",
             method, indices
@@ -247,23 +928,896 @@ pub async fn check_balances(urls: Vec<Url>) -> Result<()> {
     Ok(())
 }
 
-// Random slot in the last 64 slots on prater.
-fn random_slot(rng: &mut ThreadRng) -> u64 {
-    let c = clock::for_goerli();
-    let curr = c.current_slot();
-    rng.gen_range(curr - 64..curr)
-}
+pub async fn check_proposer_duties(urls: Vec<Url>) -> Result<()> {
+    let mut responses: Vec<(Root, Vec<ProposerDutyExt>)> = vec![];
+    let mut responders: Vec<Url> = vec![];
+    let mut latencies = vec![];
+    let mut succeeded = 0;
 
-fn random_state_id() -> StateIdExt {
-    let mut ids = vec![StateId::Finalized, StateId::Justified, StateId::Head];
-    let mut rng = rand::thread_rng();
-    let slot: u64 = random_slot(&mut rng);
-    ids.push(StateId::Slot(slot));
-    match ids.choose(&mut rng).unwrap() {
-        &StateId::Finalized => StateIdExt {
-            inner: StateId::Finalized,
-        },
-        &StateId::Justified => StateIdExt {
+    let epoch = random_duties_epoch();
+    let method = format!("/eth/v1/validator/duties/proposer/{}", epoch);
+    for u in urls.iter() {
+        let client = crate::http::api_client(u);
+        let start = Instant::now();
+        info!("Calling {} endpoint={}", method, u);
+        let (dependent_root, mut duties) = match client.get_proposer_duties(epoch).await {
+            Ok(res) => res,
+            Err(e) => {
+                warn!("Request method={} endpoint={} failed {:?}", method, u, e);
+                crate::slo::observe_failure("get_proposer_duties", u);
+                continue;
+            }
+        };
+        crate::slo::observe("get_proposer_duties", &method, u, start.elapsed());
+        latencies.push(start.elapsed().as_millis() as u64);
+        // Sort by slot.
+        duties.sort_by(|a, b| a.slot.cmp(&b.slot));
+        let ext = duties
+            .into_iter()
+            .map(|d| ProposerDutyExt { inner: d })
+            .collect();
+        responses.push((dependent_root, ext));
+        responders.push(u.clone());
+        succeeded += 1;
+    }
+    let median_latency = Duration::from_millis(median(&mut latencies));
+    info!(
+        "{} median_response_time={}",
+        method,
+        human_duration(&median_latency),
+    );
+    crate::metrics::update(|| {
+        crate::metrics::GET_PROPOSER_DUTIES_LATENCY_MILLISECONDS
+            .observe(median_latency.as_millis() as f64)
+    });
+
+    if mismatched_responses(&method, &responders, &responses, succeeded) {
+        crate::metrics::update(|| crate::metrics::PROPOSER_DUTIES_NOT_EQUAL_TOTAL.inc());
+        warn!("MISMATCHED REQUEST: endpoint={}", method);
+        warn!("Responses: {:?}", responses);
+    }
+    Ok(())
+}
+
+pub async fn check_attester_duties(urls: Vec<Url>) -> Result<()> {
+    let mut responses: Vec<(Root, Vec<AttestationDutyExt>)> = vec![];
+    let mut responders: Vec<Url> = vec![];
+    let mut latencies = vec![];
+    let mut succeeded = 0;
+
+    let indices = random_indices();
+    let epoch = random_duties_epoch();
+    let method = format!("/eth/v1/validator/duties/attester/{}", epoch);
+    for u in urls.iter() {
+        let client = crate::http::api_client(u);
+        let start = Instant::now();
+        info!(
+            "Calling {} endpoint={}, num_indices={}",
+            method,
+            u,
+            indices.len(),
+        );
+        let (dependent_root, mut duties) = match client.get_attester_duties(epoch, &indices).await {
+            Ok(res) => res,
+            Err(e) => {
+                warn!("Request method={} endpoint={} failed {:?}", method, u, e);
+                crate::slo::observe_failure("get_attester_duties", u);
+                continue;
+            }
+        };
+        crate::slo::observe("get_attester_duties", &method, u, start.elapsed());
+        latencies.push(start.elapsed().as_millis() as u64);
+        // Sort by validator index.
+        duties.sort_by(|a, b| a.validator_index.cmp(&b.validator_index));
+        let ext = duties
+            .into_iter()
+            .map(|d| AttestationDutyExt { inner: d })
+            .collect();
+        responses.push((dependent_root, ext));
+        responders.push(u.clone());
+        succeeded += 1;
+    }
+    let median_latency = Duration::from_millis(median(&mut latencies));
+    info!(
+        "{} median_response_time={}, num_indices={}",
+        method,
+        human_duration(&median_latency),
+        indices.len(),
+    );
+    crate::metrics::update(|| {
+        crate::metrics::GET_ATTESTER_DUTIES_LATENCY_MILLISECONDS
+            .observe(median_latency.as_millis() as f64)
+    });
+
+    if mismatched_responses(&method, &responders, &responses, succeeded) {
+        crate::metrics::update(|| crate::metrics::ATTESTER_DUTIES_NOT_EQUAL_TOTAL.inc());
+        warn!(
+            "MISMATCHED REQUEST: endpoint={}, indices={:?}",
+            method, indices
+        );
+        warn!("Responses: {:?}", responses);
+    }
+    Ok(())
+}
+
+pub async fn check_sync_committee_duties(urls: Vec<Url>) -> Result<()> {
+    let mut responses: Vec<Vec<SyncCommitteeDutyExt>> = vec![];
+    let mut responders: Vec<Url> = vec![];
+    let mut latencies = vec![];
+    let mut succeeded = 0;
+
+    let indices = random_indices();
+    let epoch = random_duties_epoch();
+    let method = format!("/eth/v1/validator/duties/sync/{}", epoch);
+    for u in urls.iter() {
+        let client = crate::http::api_client(u);
+        let start = Instant::now();
+        info!(
+            "Calling {} endpoint={}, num_indices={}",
+            method,
+            u,
+            indices.len(),
+        );
+        let mut duties = match client.get_sync_committee_duties(epoch, &indices).await {
+            Ok(res) => res,
+            Err(e) => {
+                warn!("Request method={} endpoint={} failed {:?}", method, u, e);
+                crate::slo::observe_failure("get_sync_committee_duties", u);
+                continue;
+            }
+        };
+        crate::slo::observe("get_sync_committee_duties", &method, u, start.elapsed());
+        latencies.push(start.elapsed().as_millis() as u64);
+        // Sort by validator index.
+        duties.sort_by(|a, b| a.validator_index.cmp(&b.validator_index));
+        let ext = duties
+            .into_iter()
+            .map(|d| SyncCommitteeDutyExt { inner: d })
+            .collect();
+        responses.push(ext);
+        responders.push(u.clone());
+        succeeded += 1;
+    }
+    let median_latency = Duration::from_millis(median(&mut latencies));
+    info!(
+        "{} median_response_time={}, num_indices={}",
+        method,
+        human_duration(&median_latency),
+        indices.len(),
+    );
+    crate::metrics::update(|| {
+        crate::metrics::GET_SYNC_COMMITTEE_DUTIES_LATENCY_MILLISECONDS
+            .observe(median_latency.as_millis() as f64)
+    });
+
+    if mismatched_responses(&method, &responders, &responses, succeeded) {
+        crate::metrics::update(|| crate::metrics::SYNC_COMMITTEE_DUTIES_NOT_EQUAL_TOTAL.inc());
+        warn!(
+            "MISMATCHED REQUEST: endpoint={}, indices={:?}",
+            method, indices
+        );
+        warn!("Responses: {:?}", responses);
+    }
+    Ok(())
+}
+
+pub async fn check_block_rewards(urls: Vec<Url>) -> Result<()> {
+    let mut responses: Vec<BlockRewards> = vec![];
+    let mut responders: Vec<Url> = vec![];
+    let mut latencies = vec![];
+    let mut succeeded = 0;
+
+    let id = random_block_id();
+    let method = format!("/eth/v1/beacon/rewards/blocks/{}", id.inner);
+    for u in urls.iter() {
+        let start = Instant::now();
+        info!("Calling {} endpoint={}", method, u);
+        let rewards: BlockRewards = match crate::http::get(u, &method[1..]).await {
+            Ok(res) => res,
+            Err(e) => {
+                warn!("Request method={} endpoint={} failed {:?}", method, u, e);
+                crate::slo::observe_failure("get_block_rewards", u);
+                continue;
+            }
+        };
+        crate::slo::observe("get_block_rewards", &method, u, start.elapsed());
+        latencies.push(start.elapsed().as_millis() as u64);
+        responses.push(rewards);
+        responders.push(u.clone());
+        succeeded += 1;
+    }
+    let median_latency = Duration::from_millis(median(&mut latencies));
+    info!(
+        "{} median_response_time={}",
+        method,
+        human_duration(&median_latency),
+    );
+    crate::metrics::update(|| {
+        crate::metrics::GET_BLOCK_REWARDS_LATENCY_MILLISECONDS
+            .observe(median_latency.as_millis() as f64)
+    });
+
+    if mismatched_responses(&method, &responders, &responses, succeeded) {
+        crate::metrics::update(|| crate::metrics::BLOCK_REWARDS_NOT_EQUAL_TOTAL.inc());
+        warn!("MISMATCHED REQUEST: endpoint={}", method);
+        report_rewards_field_mismatches("block", &method, &responders, &responses);
+    }
+    Ok(())
+}
+
+pub async fn check_attestation_rewards(urls: Vec<Url>) -> Result<()> {
+    let mut responses: Vec<AttestationRewards> = vec![];
+    let mut responders: Vec<Url> = vec![];
+    let mut latencies = vec![];
+    let mut succeeded = 0;
+
+    let indices = random_indices();
+    let body: Vec<String> = indices.iter().map(|i| i.to_string()).collect();
+    let epoch = random_rewards_epoch();
+    let method = format!("/eth/v1/beacon/rewards/attestations/{}", epoch);
+    for u in urls.iter() {
+        let start = Instant::now();
+        info!(
+            "Calling {} endpoint={}, num_indices={}",
+            method,
+            u,
+            indices.len(),
+        );
+        let mut rewards: AttestationRewards = match crate::http::post(u, &method[1..], &body).await
+        {
+            Ok(res) => res,
+            Err(e) => {
+                warn!("Request method={} endpoint={} failed {:?}", method, u, e);
+                crate::slo::observe_failure("get_attestation_rewards", u);
+                continue;
+            }
+        };
+        crate::slo::observe("get_attestation_rewards", &method, u, start.elapsed());
+        latencies.push(start.elapsed().as_millis() as u64);
+        // Sort by effective balance and validator index respectively.
+        rewards
+            .ideal_rewards
+            .sort_by(|a, b| a.effective_balance.cmp(&b.effective_balance));
+        rewards
+            .total_rewards
+            .sort_by(|a, b| a.validator_index.cmp(&b.validator_index));
+        responses.push(rewards);
+        responders.push(u.clone());
+        succeeded += 1;
+    }
+    let median_latency = Duration::from_millis(median(&mut latencies));
+    info!(
+        "{} median_response_time={}, num_indices={}",
+        method,
+        human_duration(&median_latency),
+        indices.len(),
+    );
+    crate::metrics::update(|| {
+        crate::metrics::GET_ATTESTATION_REWARDS_LATENCY_MILLISECONDS
+            .observe(median_latency.as_millis() as f64)
+    });
+
+    if mismatched_responses(&method, &responders, &responses, succeeded) {
+        crate::metrics::update(|| crate::metrics::ATTESTATION_REWARDS_NOT_EQUAL_TOTAL.inc());
+        warn!(
+            "MISMATCHED REQUEST: endpoint={}, indices={:?}",
+            method, indices
+        );
+        report_rewards_field_mismatches("attestations", &method, &responders, &responses);
+    }
+    Ok(())
+}
+
+pub async fn check_sync_committee_rewards(urls: Vec<Url>) -> Result<()> {
+    let mut responses: Vec<Vec<SyncCommitteeReward>> = vec![];
+    let mut responders: Vec<Url> = vec![];
+    let mut latencies = vec![];
+    let mut succeeded = 0;
+
+    let indices = random_indices();
+    let body: Vec<String> = indices.iter().map(|i| i.to_string()).collect();
+    let id = random_block_id();
+    let method = format!("/eth/v1/beacon/rewards/sync_committee/{}", id.inner);
+    for u in urls.iter() {
+        let start = Instant::now();
+        info!(
+            "Calling {} endpoint={}, num_indices={}",
+            method,
+            u,
+            indices.len(),
+        );
+        let mut rewards: Vec<SyncCommitteeReward> =
+            match crate::http::post(u, &method[1..], &body).await {
+                Ok(res) => res,
+                Err(e) => {
+                    warn!("Request method={} endpoint={} failed {:?}", method, u, e);
+                    crate::slo::observe_failure("get_sync_committee_rewards", u);
+                    continue;
+                }
+            };
+        crate::slo::observe("get_sync_committee_rewards", &method, u, start.elapsed());
+        latencies.push(start.elapsed().as_millis() as u64);
+        // Sort by validator index.
+        rewards.sort_by(|a, b| a.validator_index.cmp(&b.validator_index));
+        responses.push(rewards);
+        responders.push(u.clone());
+        succeeded += 1;
+    }
+    let median_latency = Duration::from_millis(median(&mut latencies));
+    info!(
+        "{} median_response_time={}, num_indices={}",
+        method,
+        human_duration(&median_latency),
+        indices.len(),
+    );
+    crate::metrics::update(|| {
+        crate::metrics::GET_SYNC_COMMITTEE_REWARDS_LATENCY_MILLISECONDS
+            .observe(median_latency.as_millis() as f64)
+    });
+
+    if mismatched_responses(&method, &responders, &responses, succeeded) {
+        crate::metrics::update(|| crate::metrics::SYNC_COMMITTEE_REWARDS_NOT_EQUAL_TOTAL.inc());
+        warn!(
+            "MISMATCHED REQUEST: endpoint={}, indices={:?}",
+            method, indices
+        );
+        report_rewards_field_mismatches("sync_committee", &method, &responders, &responses);
+    }
+    Ok(())
+}
+
+pub async fn check_light_client_bootstrap(urls: Vec<Url>) -> Result<()> {
+    // Bootstraps are only guaranteed to be served for finalized checkpoint roots,
+    // so we ask the first available endpoint for its latest one.
+    let mut block_root = None;
+    for u in urls.iter() {
+        let path = "eth/v1/beacon/states/finalized/finality_checkpoints";
+        match crate::http::get::<serde_json::Value>(u, path).await {
+            Ok(res) => {
+                block_root = res["finalized"]["root"].as_str().map(|r| r.to_string());
+                break;
+            }
+            Err(e) => {
+                warn!("Request method=/{} endpoint={} failed {:?}", path, u, e);
+                continue;
+            }
+        }
+    }
+    let block_root = match block_root {
+        Some(root) => root,
+        None => return Ok(()),
+    };
+    let method = format!("/eth/v1/beacon/light_client/bootstrap/{}", block_root);
+    check_light_client_route(
+        "bootstrap",
+        &method,
+        &urls,
+        &crate::metrics::GET_LIGHT_CLIENT_BOOTSTRAP_LATENCY_MILLISECONDS,
+    )
+    .await
+}
+
+pub async fn check_light_client_updates(urls: Vec<Url>) -> Result<()> {
+    let c = clock::for_goerli();
+    let period = c.current_slot() / SLOTS_PER_EPOCH / EPOCHS_PER_SYNC_COMMITTEE_PERIOD;
+    let count: u64 = rand::thread_rng().gen_range(1..4);
+    let method = format!(
+        "/eth/v1/beacon/light_client/updates?start_period={}&count={}",
+        period + 1 - count,
+        count,
+    );
+    check_light_client_route(
+        "updates",
+        &method,
+        &urls,
+        &crate::metrics::GET_LIGHT_CLIENT_UPDATES_LATENCY_MILLISECONDS,
+    )
+    .await
+}
+
+pub async fn check_light_client_finality_update(urls: Vec<Url>) -> Result<()> {
+    check_light_client_route(
+        "finality_update",
+        "/eth/v1/beacon/light_client/finality_update",
+        &urls,
+        &crate::metrics::GET_LIGHT_CLIENT_FINALITY_UPDATE_LATENCY_MILLISECONDS,
+    )
+    .await
+}
+
+pub async fn check_light_client_optimistic_update(urls: Vec<Url>) -> Result<()> {
+    check_light_client_route(
+        "optimistic_update",
+        "/eth/v1/beacon/light_client/optimistic_update",
+        &urls,
+        &crate::metrics::GET_LIGHT_CLIENT_OPTIMISTIC_UPDATE_LATENCY_MILLISECONDS,
+    )
+    .await
+}
+
+pub async fn check_pool_attestations(urls: Vec<Url>) -> Result<()> {
+    check_pool("attestations", &urls).await
+}
+
+pub async fn check_pool_attester_slashings(urls: Vec<Url>) -> Result<()> {
+    check_pool("attester_slashings", &urls).await
+}
+
+pub async fn check_pool_proposer_slashings(urls: Vec<Url>) -> Result<()> {
+    check_pool("proposer_slashings", &urls).await
+}
+
+pub async fn check_pool_voluntary_exits(urls: Vec<Url>) -> Result<()> {
+    check_pool("voluntary_exits", &urls).await
+}
+
+pub async fn check_pool_bls_to_execution_changes(urls: Vec<Url>) -> Result<()> {
+    check_pool("bls_to_execution_changes", &urls).await
+}
+
+// Operation pools are local to each node and legitimately differ depending on
+// what it has seen over gossip, so rather than flagging mismatches we export how
+// much the pools overlap across endpoints.
+async fn check_pool(pool: &str, urls: &[Url]) -> Result<()> {
+    let mut pools: Vec<(Url, HashSet<String>)> = vec![];
+    let mut latencies = vec![];
+
+    let method = format!("/eth/v1/beacon/pool/{}", pool);
+    for u in urls.iter() {
+        let start = Instant::now();
+        info!("Calling {} endpoint={}", method, u);
+        let items: Vec<serde_json::Value> = match crate::http::get(u, &method[1..]).await {
+            Ok(res) => res,
+            Err(e) => {
+                warn!("Request method={} endpoint={} failed {:?}", method, u, e);
+                crate::slo::observe_failure(&format!("get_pool_{}", pool), u);
+                continue;
+            }
+        };
+        crate::slo::observe(&format!("get_pool_{}", pool), &method, u, start.elapsed());
+        latencies.push(start.elapsed().as_millis() as u64);
+        // Objects serialize with sorted keys, so equal operations
+        // always produce the same string.
+        let items: HashSet<String> = items.iter().map(|i| i.to_string()).collect();
+        crate::metrics::update(|| {
+            crate::metrics::POOL_SIZE
+                .with_label_values(&[pool, u.as_str()])
+                .set(items.len() as i64)
+        });
+        pools.push((u.clone(), items));
+    }
+    let median_latency = Duration::from_millis(median(&mut latencies));
+    info!(
+        "{} median_response_time={}",
+        method,
+        human_duration(&median_latency),
+    );
+    crate::metrics::update(|| {
+        crate::metrics::GET_POOL_LATENCY_MILLISECONDS
+            .with_label_values(&[pool])
+            .observe(median_latency.as_millis() as f64)
+    });
+
+    let union: HashSet<&String> = pools.iter().flat_map(|(_, items)| items.iter()).collect();
+    let intersection: HashSet<&String> = union
+        .iter()
+        .filter(|i| pools.iter().all(|(_, items)| items.contains(**i)))
+        .copied()
+        .collect();
+    // Empty pools everywhere are in perfect agreement.
+    let overlap = if union.is_empty() {
+        1.0
+    } else {
+        intersection.len() as f64 / union.len() as f64
+    };
+    crate::metrics::update(|| {
+        crate::metrics::POOL_OVERLAP_RATIO
+            .with_label_values(&[pool])
+            .set(overlap)
+    });
+    for (u, items) in pools.iter() {
+        let missing = union.iter().filter(|i| !items.contains(**i)).count();
+        crate::metrics::update(|| {
+            crate::metrics::POOL_MISSING
+                .with_label_values(&[pool, u.as_str()])
+                .set(missing as i64)
+        });
+    }
+    info!(
+        "{} overlap={:.2} union={} intersection={} across {} endpoints",
+        method,
+        overlap,
+        union.len(),
+        intersection.len(),
+        pools.len(),
+    );
+    Ok(())
+}
+
+pub async fn check_block_encodings(urls: Vec<Url>) -> Result<()> {
+    let method = format!("/eth/v2/beacon/blocks/{}", random_settled_id());
+    check_ssz(Object::Block, &method, &urls, true).await
+}
+
+pub async fn check_block_ssz(urls: Vec<Url>) -> Result<()> {
+    let method = format!("/eth/v2/beacon/blocks/{}", random_settled_id());
+    check_ssz(Object::Block, &method, &urls, false).await
+}
+
+pub async fn check_state_encodings(urls: Vec<Url>) -> Result<()> {
+    let method = format!("/eth/v2/debug/beacon/states/{}", random_settled_id());
+    check_ssz(Object::State, &method, &urls, true).await
+}
+
+pub async fn check_state_ssz(urls: Vec<Url>) -> Result<()> {
+    let method = format!("/eth/v2/debug/beacon/states/{}", random_settled_id());
+    check_ssz(Object::State, &method, &urls, false).await
+}
+
+pub async fn check_blob_sidecar_encodings(urls: Vec<Url>) -> Result<()> {
+    let method = format!("/eth/v1/beacon/blob_sidecars/{}", random_settled_id());
+    check_ssz(Object::BlobSidecars, &method, &urls, true).await
+}
+
+pub async fn check_blob_sidecars_ssz(urls: Vec<Url>) -> Result<()> {
+    let method = format!("/eth/v1/beacon/blob_sidecars/{}", random_settled_id());
+    check_ssz(Object::BlobSidecars, &method, &urls, false).await
+}
+
+// Compares the SSZ encoding of an object across nodes and, if requested,
+// verifies that each node's JSON encoding decodes to the same object.
+async fn check_ssz(object: Object, method: &str, urls: &[Url], with_json: bool) -> Result<()> {
+    let mut responses: Vec<Vec<u8>> = vec![];
+    let mut responders: Vec<Url> = vec![];
+    let mut latencies = vec![];
+    let mut succeeded = 0;
+
+    for u in urls.iter() {
+        let start = Instant::now();
+        info!("Calling {} endpoint={}, with_json={}", method, u, with_json);
+        let ssz = if with_json {
+            match crate::encoding::fetch_both(object, u, &method[1..]).await {
+                Ok((None, ssz)) => {
+                    info!(
+                        "Skipping json and ssz comparison of unsupported version method={} endpoint={}",
+                        method, u,
+                    );
+                    ssz
+                }
+                Ok((Some(agree), ssz)) => {
+                    if !agree {
+                        crate::metrics::update(|| {
+                            crate::metrics::ENCODINGS_INCONSISTENT_TOTAL
+                                .with_label_values(&[object.name(), u.as_str()])
+                                .inc()
+                        });
+                        warn!(
+                            "INCONSISTENT ENCODINGS: endpoint={} method={} json and ssz disagree",
+                            u, method,
+                        );
+                    }
+                    ssz
+                }
+                Err(e) => {
+                    warn!("Request method={} endpoint={} failed {:?}", method, u, e);
+                    crate::slo::observe_failure(&format!("get_{}_ssz", object.name()), u);
+                    continue;
+                }
+            }
+        } else {
+            match crate::http::get_ssz(u, &method[1..]).await {
+                Ok((_, ssz)) => ssz,
+                Err(e) => {
+                    warn!("Request method={} endpoint={} failed {:?}", method, u, e);
+                    crate::slo::observe_failure(&format!("get_{}_ssz", object.name()), u);
+                    continue;
+                }
+            }
+        };
+        crate::slo::observe(
+            &format!("get_{}_ssz", object.name()),
+            method,
+            u,
+            start.elapsed(),
+        );
+        latencies.push(start.elapsed().as_millis() as u64);
+        responses.push(ssz);
+        responders.push(u.clone());
+        succeeded += 1;
+    }
+    let median_latency = Duration::from_millis(median(&mut latencies));
+    info!(
+        "{} median_response_time={}",
+        method,
+        human_duration(&median_latency),
+    );
+    crate::metrics::update(|| {
+        crate::metrics::GET_SSZ_LATENCY_MILLISECONDS
+            .with_label_values(&[object.name()])
+            .observe(median_latency.as_millis() as f64)
+    });
+
+    if mismatched_responses(method, &responders, &responses, succeeded) {
+        crate::metrics::update(|| {
+            crate::metrics::SSZ_NOT_EQUAL_TOTAL
+                .with_label_values(&[object.name()])
+                .inc()
+        });
+        // SSZ payloads are too large to log in full.
+        let sizes: Vec<usize> = responses.iter().map(|r| r.len()).collect();
+        warn!(
+            "MISMATCHED REQUEST: endpoint={}, ssz_sizes={:?}",
+            method, sizes
+        );
+    }
+    Ok(())
+}
+
+// Light client support is optional and still being rolled out across clients,
+// so endpoints which do not implement a route are counted as unsupported and
+// excluded from the comparison instead of being reported as mismatched.
+async fn check_light_client_route(
+    route: &str,
+    method: &str,
+    urls: &[Url],
+    latency: &prometheus::Histogram,
+) -> Result<()> {
+    let mut responses: Vec<serde_json::Value> = vec![];
+    let mut responders: Vec<Url> = vec![];
+    let mut latencies = vec![];
+    let mut succeeded = 0;
+
+    for u in urls.iter() {
+        let start = Instant::now();
+        info!("Calling {} endpoint={}", method, u);
+        let res: serde_json::Value = match crate::http::get_raw(u, &method[1..]).await {
+            Ok(res) => res,
+            Err(e) if e.is_unsupported() => {
+                info!("Unsupported method={} endpoint={}", method, u);
+                crate::metrics::update(|| {
+                    crate::metrics::LIGHT_CLIENT_UNSUPPORTED_TOTAL
+                        .with_label_values(&[route, u.as_str()])
+                        .inc()
+                });
+                continue;
+            }
+            Err(e) => {
+                warn!("Request method={} endpoint={} failed {:?}", method, u, e);
+                crate::slo::observe_failure(&format!("get_light_client_{}", route), u);
+                continue;
+            }
+        };
+        crate::slo::observe(
+            &format!("get_light_client_{}", route),
+            method,
+            u,
+            start.elapsed(),
+        );
+        latencies.push(start.elapsed().as_millis() as u64);
+        responses.push(res);
+        responders.push(u.clone());
+        succeeded += 1;
+    }
+    let median_latency = Duration::from_millis(median(&mut latencies));
+    info!(
+        "{} median_response_time={}",
+        method,
+        human_duration(&median_latency),
+    );
+    crate::metrics::update(|| latency.observe(median_latency.as_millis() as f64));
+
+    if mismatched_responses(method, &responders, &responses, succeeded) {
+        crate::metrics::update(|| {
+            crate::metrics::LIGHT_CLIENT_NOT_EQUAL_TOTAL
+                .with_label_values(&[route])
+                .inc()
+        });
+        warn!("MISMATCHED REQUEST: endpoint={}", method);
+        warn!("Responses: {:?}", responses);
+    }
+    Ok(())
+}
+
+pub async fn check_blob_sidecars(urls: Vec<Url>) -> Result<()> {
+    let mut responses: Vec<Vec<BlobSidecar>> = vec![];
+    let mut responders: Vec<Url> = vec![];
+    let mut latencies = vec![];
+    let mut succeeded = 0;
+
+    let id = random_block_id();
+    let indices = random_blob_indices();
+    let query = if indices.is_empty() {
+        String::new()
+    } else {
+        let indices: Vec<String> = indices.iter().map(|i| i.to_string()).collect();
+        format!("?indices={}", indices.join(","))
+    };
+    let method = format!("/eth/v1/beacon/blob_sidecars/{}{}", id.inner, query);
+    for u in urls.iter() {
+        // Pin the id to the root of the block the endpoint serves for it, so
+        // the sidecars and the block they are verified against are the same
+        // block even if the head moves in between.
+        let pinned = match pin_block_id(u, &id).await {
+            Ok(pinned) => pinned,
+            Err(e) => {
+                warn!(
+                    "Request method=/eth/v1/beacon/blocks/{}/root endpoint={} failed {:?}",
+                    id.inner, u, e
+                );
+                continue;
+            }
+        };
+        let path = format!("eth/v1/beacon/blob_sidecars/{}{}", pinned.inner, query);
+        let start = Instant::now();
+        info!("Calling {} endpoint={}", method, u);
+        let mut sidecars: Vec<BlobSidecar> = match crate::http::get(u, &path).await {
+            Ok(res) => res,
+            Err(e) => {
+                warn!("Request method={} endpoint={} failed {:?}", method, u, e);
+                crate::slo::observe_failure("get_blob_sidecars", u);
+                continue;
+            }
+        };
+        crate::slo::observe("get_blob_sidecars", &method, u, start.elapsed());
+        latencies.push(start.elapsed().as_millis() as u64);
+        // Sort by blob index.
+        sidecars.sort_by(|a, b| a.index.cmp(&b.index));
+        check_blob_commitments(u, &pinned, &sidecars).await;
+        responses.push(sidecars);
+        responders.push(u.clone());
+        succeeded += 1;
+    }
+    let median_latency = Duration::from_millis(median(&mut latencies));
+    info!(
+        "{} median_response_time={}",
+        method,
+        human_duration(&median_latency),
+    );
+    crate::metrics::update(|| {
+        crate::metrics::GET_BLOB_SIDECARS_LATENCY_MILLISECONDS
+            .observe(median_latency.as_millis() as f64)
+    });
+
+    if mismatched_responses(&method, &responders, &responses, succeeded) {
+        crate::metrics::update(|| crate::metrics::BLOB_SIDECARS_NOT_EQUAL_TOTAL.inc());
+        warn!("MISMATCHED REQUEST: endpoint={}", method);
+        report_field_mismatches(&method, &responders, &responses);
+    }
+    Ok(())
+}
+
+// Resolves a block id to the root of the block an endpoint serves for it.
+async fn pin_block_id(u: &Url, id: &BlockIdExt) -> Result<BlockIdExt, crate::http::Error> {
+    if let BlockId::Root(_) = id.inner {
+        return Ok(id.clone());
+    }
+    let path = format!("eth/v1/beacon/blocks/{}/root", id.inner);
+    let res: RootData = crate::http::get(u, &path).await?;
+    Ok(BlockIdExt {
+        inner: BlockId::Root(res.root),
+    })
+}
+
+// Verifies that the KZG commitments of the sidecars served by an endpoint
+// match the commitments in the body of the block it serves for the same root.
+async fn check_blob_commitments(u: &Url, id: &BlockIdExt, sidecars: &[BlobSidecar]) {
+    let path = format!("eth/v2/beacon/blocks/{}", id.inner);
+    let block: serde_json::Value = match crate::http::get(u, &path).await {
+        Ok(res) => res,
+        Err(e) => {
+            warn!("Request method=/{} endpoint={} failed {:?}", path, u, e);
+            return;
+        }
+    };
+    let commitments: Vec<&str> = block["message"]["body"]["blob_kzg_commitments"]
+        .as_array()
+        .map(|c| c.iter().filter_map(|v| v.as_str()).collect())
+        .unwrap_or_default();
+    for sidecar in sidecars.iter() {
+        let expected = commitments.get(sidecar.index as usize);
+        if expected != Some(&sidecar.kzg_commitment.as_str()) {
+            crate::metrics::update(|| {
+                crate::metrics::BLOB_COMMITMENTS_INCONSISTENT_TOTAL
+                    .with_label_values(&[u.as_str()])
+                    .inc()
+            });
+            warn!(
+                "INCONSISTENT BLOB SIDECAR: endpoint={} block_id={} index={} sidecar_commitment={} block_commitment={:?}",
+                u, id.inner, sidecar.index, sidecar.kzg_commitment, expected,
+            );
+        }
+    }
+}
+
+/// Logs which fields of each rewards response differ from the first
+/// successful response and counts them by rewards kind and field.
+fn report_rewards_field_mismatches<T: FieldDiff + std::fmt::Debug>(
+    kind: &str,
+    method: &str,
+    responders: &[Url],
+    responses: &[T],
+) {
+    for field in report_field_mismatches(method, responders, responses) {
+        crate::metrics::update(|| {
+            crate::metrics::REWARDS_FIELD_NOT_EQUAL_TOTAL
+                .with_label_values(&[kind, &field])
+                .inc()
+        });
+    }
+}
+
+/// Logs which fields of each response differ from the first successful
+/// response, returning them without their indices.
+fn report_field_mismatches<T: FieldDiff + std::fmt::Debug>(
+    method: &str,
+    responders: &[Url],
+    responses: &[T],
+) -> Vec<String> {
+    let mut fields = vec![];
+    let (first, rest) = match responses.split_first() {
+        Some(split) => split,
+        None => return fields,
+    };
+    for (i, r) in rest.iter().enumerate() {
+        let diffs = first.field_diff(r);
+        if diffs.is_empty() {
+            continue;
+        }
+        warn!(
+            "Urls {} and {} got mismatched {} fields {:?}",
+            responders[0],
+            responders[i + 1],
+            method,
+            diffs,
+        );
+        warn!("Responses: {:?} and {:?}", first, r);
+        fields.extend(diffs.iter().map(|d| strip_indices(d)));
+    }
+    fields
+}
+
+// Removes the per-validator indices from a field path so it can be used
+// as a metric label without unbounded cardinality.
+fn strip_indices(field: &str) -> String {
+    let mut out = String::with_capacity(field.len());
+    let mut in_brackets = false;
+    for c in field.chars() {
+        match c {
+            '[' => {
+                in_brackets = true;
+                out.push(c);
+            }
+            ']' => {
+                in_brackets = false;
+                out.push(c);
+            }
+            _ if in_brackets => {}
+            _ => out.push(c),
+        }
+    }
+    out
+}
+
+// Random slot in the last 64 slots on prater.
+fn random_slot(rng: &mut ThreadRng) -> u64 {
+    let c = clock::for_goerli();
+    let curr = c.current_slot();
+    rng.gen_range(curr - 64..curr)
+}
+
+fn random_state_id() -> StateIdExt {
+    if let Some(id) = crate::control::params().and_then(|p| p.state_id) {
+        return id;
+    }
+    let mut ids = vec![StateId::Finalized, StateId::Justified, StateId::Head];
+    let mut rng = rand::thread_rng();
+    let slot: u64 = random_slot(&mut rng);
+    ids.push(StateId::Slot(slot));
+    match ids.choose(&mut rng).unwrap() {
+        &StateId::Finalized => StateIdExt {
+            inner: StateId::Finalized,
+        },
+        &StateId::Justified => StateIdExt {
             inner: StateId::Justified,
         },
         &StateId::Head => StateIdExt {
@@ -276,18 +1830,80 @@ fn random_state_id() -> StateIdExt {
     }
 }
 
-fn random_validator_indices() -> Vec<PublicKeyOrIndex> {
-    let mut indices: Vec<PublicKeyOrIndex> = vec![];
+// Either the current or the next epoch on prater, as duties can be
+// requested up to one epoch ahead.
+pub(crate) fn random_duties_epoch() -> Epoch {
+    if let Some(epoch) = crate::control::params().and_then(|p| p.epoch) {
+        return epoch;
+    }
+    let c = clock::for_goerli();
+    let curr = c.current_slot() / SLOTS_PER_EPOCH;
+    let mut rng = rand::thread_rng();
+    curr + rng.gen_range(0..2)
+}
+
+// One of the last two epochs on prater for which attestation rewards
+// can be computed.
+fn random_rewards_epoch() -> Epoch {
+    if let Some(epoch) = crate::control::params().and_then(|p| p.epoch) {
+        return epoch;
+    }
+    let c = clock::for_goerli();
+    let curr = c.current_slot() / SLOTS_PER_EPOCH;
+    let mut rng = rand::thread_rng();
+    curr - rng.gen_range(2..4)
+}
+
+// Either no indices, requesting every blob in the block, or a random
+// subset of the possible blob indices.
+fn random_blob_indices() -> Vec<u64> {
+    let mut rng = rand::thread_rng();
+    if rng.gen_bool(0.5) {
+        return vec![];
+    }
+    (0..MAX_BLOBS_PER_BLOCK)
+        .filter(|_| rng.gen_bool(0.5))
+        .collect()
+}
+
+pub(crate) fn random_indices() -> Vec<ValidatorIndex> {
+    if let Some(indices) = crate::control::params().and_then(|p| p.indices) {
+        return indices;
+    }
+    let mut indices: Vec<ValidatorIndex> = vec![];
     let mut rng = rand::thread_rng();
     let num_elems: u64 = rng.gen_range(1..100);
     for _ in 0..num_elems {
         let idx: usize = rng.gen_range(0..500_000);
-        indices.push(PublicKeyOrIndex::from(ValidatorIndex::from(idx)));
+        indices.push(ValidatorIndex::from(idx));
     }
     indices
 }
 
+// Either the finalized id or a random recent slot. Ids relative to the head
+// are avoided so consecutive requests to a node return the same object.
+pub(crate) fn random_settled_id() -> String {
+    if let Some(id) = crate::control::params().and_then(|p| p.state_id) {
+        return id.inner.to_string();
+    }
+    let mut rng = rand::thread_rng();
+    if rng.gen_bool(0.5) {
+        return "finalized".to_string();
+    }
+    random_slot(&mut rng).to_string()
+}
+
+fn random_validator_indices() -> Vec<PublicKeyOrIndex> {
+    random_indices()
+        .into_iter()
+        .map(PublicKeyOrIndex::from)
+        .collect()
+}
+
 fn random_block_id() -> BlockIdExt {
+    if let Some(id) = crate::control::params().and_then(|p| p.block_id) {
+        return id;
+    }
     let mut ids = vec![BlockId::Finalized, BlockId::Head];
     let mut rng = rand::thread_rng();
     let slot: u64 = random_slot(&mut rng);
@@ -309,7 +1925,9 @@ fn random_block_id() -> BlockIdExt {
     }
 }
 
-pub fn mismatched_responses<T: Eq>(
+/// Compares the responses of the endpoints which answered a request, given
+/// in the same order as their responses. Returns whether they mismatched.
+pub fn mismatched_responses<T: Eq + std::fmt::Debug>(
     method: &str,
     endpoints: &[Url],
     v: &Vec<T>,
@@ -319,18 +1937,49 @@ pub fn mismatched_responses<T: Eq>(
         for (j, v2) in v[i..].iter().enumerate() {
             if v1 != v2 {
                 let e1 = endpoints.get(i).unwrap();
-                let e2 = endpoints.get(j).unwrap();
-                warn!("Urls {} and {} got mismatched {} responses", method, e1, e2);
+                let e2 = endpoints.get(i + j).unwrap();
+                warn!(
+                    "Urls {}{} and {}{} got mismatched {} responses",
+                    e1,
+                    crate::node_status::annotation(e1),
+                    e2,
+                    crate::node_status::annotation(e2),
+                    method,
+                );
+                let divergent = divergent(v);
+                crate::incidents::comparison(method, endpoints, &divergent);
+                crate::history::record(method, endpoints, v, &divergent);
                 return true;
             }
         }
     }
     if endpoints.len() > 1 {
         info!("Got equal {} across {} endpoints", method, succeeded);
+        crate::incidents::comparison(method, endpoints, &[]);
+        crate::history::record(method, endpoints, v, &[]);
     }
     false
 }
 
+// Returns the indices of the responses which differ from the response a
+// strict majority of endpoints agree on. Without such a majority, as when
+// two endpoints disagree, there is no telling who is wrong, so every
+// response is divergent.
+fn divergent<T: Eq>(v: &[T]) -> Vec<usize> {
+    let agreeing: Vec<usize> = v
+        .iter()
+        .map(|a| v.iter().filter(|b| a == *b).count())
+        .collect();
+    let majority = agreeing.iter().copied().max().unwrap_or(0);
+    if majority == v.len() {
+        return vec![];
+    }
+    if majority * 2 <= v.len() {
+        return (0..v.len()).collect();
+    }
+    (0..v.len()).filter(|i| agreeing[*i] < majority).collect()
+}
+
 fn median(latencies: &mut Vec<u64>) -> u64 {
     if latencies.len() == 0 {
         return 0;
@@ -344,3 +1993,18 @@ fn median(latencies: &mut Vec<u64>) -> u64 {
         latencies[(latencies.len() / 2)]
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_divergent() {
+        assert!(divergent::<u8>(&[]).is_empty());
+        assert!(divergent(&[1, 1, 1]).is_empty());
+        assert_eq!(divergent(&[1, 1, 2]), vec![2]);
+        assert_eq!(divergent(&[1, 2]), vec![0, 1]);
+        assert_eq!(divergent(&[1, 1, 2, 2]), vec![0, 1, 2, 3]);
+        assert_eq!(divergent(&[1, 2, 3]), vec![0, 1, 2]);
+    }
+}
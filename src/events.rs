@@ -0,0 +1,243 @@
+use lazy_static::lazy_static;
+use std::collections::{HashMap, HashSet};
+use std::sync::Mutex;
+use tokio::time::{interval, sleep, Duration, Instant};
+use tracing::{info, warn};
+use url::Url;
+
+/// The event topics to subscribe to on every endpoint.
+pub const TOPICS: &[&str] = &[
+    "head",
+    "block",
+    "attestation",
+    "finalized_checkpoint",
+    "chain_reorg",
+    "voluntary_exit",
+    "blob_sidecar",
+    "payload_attributes",
+];
+
+/// Topics whose events every endpoint is not expected to emit, so they are
+/// never reported late or missing. Nodes only see the attestations of the
+/// subnets they subscribe to, only emit payload attributes for proposers
+/// which prepared with them and only reorg under their own fork choice.
+const UNCORRELATED: &[&str] = &["attestation", "payload_attributes", "chain_reorg"];
+
+/// How long an event is remembered after it was reported, so endpoints which
+/// emit it even later don't start a new observation of it and have every
+/// other endpoint reported missing.
+const TOMBSTONE_TTL: Duration = Duration::from_secs(600);
+
+/// How long to wait before resubscribing to an endpoint whose stream failed.
+const RECONNECT_DELAY: Duration = Duration::from_secs(5);
+
+/// An event seen by at least one endpoint, identified by its topic and a key
+/// which every endpoint should agree on, such as the slot of a head event.
+struct Observation {
+    first_seen: Instant,
+    /// When late and missing endpoints were reported for the event.
+    swept_at: Option<Instant>,
+    /// When each endpoint emitted the event and the value it reported for it.
+    seen_by: HashMap<Url, (Instant, String)>,
+}
+
+lazy_static! {
+    static ref OBSERVATIONS: Mutex<HashMap<(String, String), Observation>> =
+        Mutex::new(HashMap::new());
+    /// The topics endpoints rejected, which they are not expected to emit.
+    static ref UNSUPPORTED: Mutex<HashSet<(String, Url)>> = Mutex::new(HashSet::new());
+}
+
+/// Subscribes to the event stream of every endpoint and correlates their events,
+/// reporting events which arrive late, never arrive or contradict each other.
+pub async fn run_event_monitor(endpoints: Vec<Url>, late_after: Duration, missing_after: Duration) {
+    info!("Starting event stream monitor");
+    for u in endpoints.iter() {
+        for topic in TOPICS.iter() {
+            tokio::spawn(subscribe(u.clone(), topic));
+        }
+    }
+    let mut ticker = interval(Duration::from_secs(1));
+    loop {
+        ticker.tick().await;
+        sweep(&endpoints, late_after, missing_after);
+    }
+}
+
+// Subscribes to a single topic, so an endpoint which rejects one topic
+// still streams the others.
+async fn subscribe(u: Url, topic: &'static str) {
+    let path = format!("eth/v1/events?topics={}", topic);
+    loop {
+        info!("Subscribing to /{} endpoint={}", path, u);
+        match stream_events(&u, &path).await {
+            Err(e) if is_rejected(&e) => {
+                warn!(
+                    "Event topic={} is unsupported endpoint={} {:?}",
+                    topic, u, e
+                );
+                UNSUPPORTED
+                    .lock()
+                    .unwrap()
+                    .insert((topic.to_string(), u.clone()));
+                return;
+            }
+            Err(e) => warn!("Event stream topic={} endpoint={} failed {:?}", topic, u, e),
+            Ok(()) => {}
+        }
+        sleep(RECONNECT_DELAY).await;
+    }
+}
+
+// Whether an endpoint refused a subscription outright, as nodes answer
+// 400 to topics they don't know.
+fn is_rejected(e: &eyre::Report) -> bool {
+    e.downcast_ref::<reqwest::Error>()
+        .and_then(|e| e.status())
+        .map(|s| s.is_client_error())
+        .unwrap_or(false)
+}
+
+async fn stream_events(u: &Url, path: &str) -> eyre::Result<()> {
+    let target = crate::http::target(u).join(path)?;
+    let mut response = crate::http::stream_client()
+        .get(target)
+        .header("Accept", "text/event-stream")
+        .send()
+        .await?
+        .error_for_status()?;
+    let mut buf = String::new();
+    while let Some(chunk) = response.chunk().await? {
+        buf.push_str(&String::from_utf8_lossy(&chunk));
+        // Normalize line endings so messages are always separated by a blank line.
+        if buf.contains('\r') {
+            buf = buf.replace("\r\n", "\n");
+        }
+        while let Some(end) = buf.find("\n\n") {
+            let message: String = buf.drain(..end + 2).collect();
+            if let Some((topic, data)) = parse_message(&message) {
+                observe(u, &topic, &data);
+            }
+        }
+    }
+    Err(eyre::eyre!("stream closed"))
+}
+
+// Parses a server-sent event message into its event name and data.
+fn parse_message(message: &str) -> Option<(String, serde_json::Value)> {
+    let mut topic = None;
+    let mut data = String::new();
+    for line in message.lines() {
+        if let Some(event) = line.strip_prefix("event:") {
+            topic = Some(event.trim().to_string());
+        } else if let Some(d) = line.strip_prefix("data:") {
+            data.push_str(d.trim());
+        }
+    }
+    let data = serde_json::from_str(&data).ok()?;
+    Some((topic?, data))
+}
+
+// Returns the key which identifies an event across endpoints and the value
+// every endpoint should report for it.
+fn correlate(topic: &str, data: &serde_json::Value) -> Option<(String, String)> {
+    let field = |v: &serde_json::Value| v.as_str().map(|s| s.to_string());
+    match topic {
+        "head" | "block" => Some((field(&data["slot"])?, field(&data["block"])?)),
+        // Attestations are told apart by their signature.
+        "attestation" => Some((field(&data["signature"])?, data.to_string())),
+        "finalized_checkpoint" => Some((field(&data["epoch"])?, field(&data["block"])?)),
+        "chain_reorg" => Some((field(&data["slot"])?, field(&data["new_head_block"])?)),
+        "voluntary_exit" => Some((
+            field(&data["message"]["validator_index"])?,
+            data.to_string(),
+        )),
+        "blob_sidecar" => Some((
+            format!("{}:{}", field(&data["block_root"])?, field(&data["index"])?),
+            field(&data["kzg_commitment"])?,
+        )),
+        "payload_attributes" => Some((
+            field(&data["data"]["proposal_slot"])?,
+            field(&data["data"]["parent_block_root"])?,
+        )),
+        _ => None,
+    }
+}
+
+fn observe(u: &Url, topic: &str, data: &serde_json::Value) {
+    crate::metrics::EVENTS_RECEIVED_TOTAL
+        .with_label_values(&[topic, u.as_str()])
+        .inc();
+    let (key, value) = match correlate(topic, data) {
+        Some(res) => res,
+        None => {
+            warn!("Malformed {} event endpoint={}: {}", topic, u, data);
+            return;
+        }
+    };
+    let now = Instant::now();
+    let mut observations = OBSERVATIONS.lock().unwrap();
+    let observation = observations
+        .entry((topic.to_string(), key.clone()))
+        .or_insert_with(|| Observation {
+            first_seen: now,
+            swept_at: None,
+            seen_by: HashMap::new(),
+        });
+    let delay = now.duration_since(observation.first_seen);
+    crate::metrics::EVENT_DELAY_MILLISECONDS
+        .with_label_values(&[topic, u.as_str()])
+        .observe(delay.as_millis() as f64);
+    for (other, (_, other_value)) in observation.seen_by.iter() {
+        if other != u && *other_value != value {
+            crate::metrics::EVENTS_CONTRADICTORY_TOTAL
+                .with_label_values(&[topic, u.as_str()])
+                .inc();
+            warn!(
+                "CONTRADICTORY EVENT: topic={} key={} endpoint={} value={} endpoint={} value={}",
+                topic, key, u, value, other, other_value,
+            );
+            break;
+        }
+    }
+    observation.seen_by.insert(u.clone(), (now, value));
+}
+
+// Reports events which some endpoints emitted late or not at all once every
+// endpoint had enough time to emit them, and forgets them a while later.
+fn sweep(endpoints: &[Url], late_after: Duration, missing_after: Duration) {
+    let unsupported = UNSUPPORTED.lock().unwrap();
+    let mut observations = OBSERVATIONS.lock().unwrap();
+    observations.retain(|(topic, key), observation| {
+        if let Some(swept_at) = observation.swept_at {
+            return swept_at.elapsed() < TOMBSTONE_TTL;
+        }
+        if observation.first_seen.elapsed() < missing_after {
+            return true;
+        }
+        if UNCORRELATED.contains(&topic.as_str()) {
+            return false;
+        }
+        observation.swept_at = Some(Instant::now());
+        for u in endpoints.iter() {
+            match observation.seen_by.get(u) {
+                Some((seen, _)) => {
+                    if seen.duration_since(observation.first_seen) > late_after {
+                        crate::metrics::EVENTS_LATE_TOTAL
+                            .with_label_values(&[topic, u.as_str()])
+                            .inc();
+                        warn!("LATE EVENT: topic={} key={} endpoint={}", topic, key, u);
+                    }
+                }
+                None if unsupported.contains(&(topic.clone(), u.clone())) => {}
+                None => {
+                    crate::metrics::EVENTS_MISSING_TOTAL
+                        .with_label_values(&[topic, u.as_str()])
+                        .inc();
+                    warn!("MISSING EVENT: topic={} key={} endpoint={}", topic, key, u);
+                }
+            }
+        }
+        true
+    });
+}
@@ -0,0 +1,185 @@
+use crate::api_checker::SharedEndpoints;
+use crate::types::BeaconHeader;
+use lazy_static::lazy_static;
+use std::collections::{BTreeMap, HashMap};
+use std::sync::RwLock;
+use tokio::time::{interval, Duration};
+use tracing::{info, warn};
+use url::Url;
+
+/// How many slots of head history to keep per endpoint for reorg detection.
+const HEAD_HISTORY_SLOTS: u64 = 64;
+
+/// The head an endpoint reported at its last poll.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Head {
+    pub slot: u64,
+    pub root: String,
+}
+
+/// Why the heads of a set of endpoints differ, used to explain
+/// mismatches of checks against the head.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HeadDivergence {
+    /// Every endpoint reports the same head, so a mismatch is a real bug.
+    None,
+    /// Some endpoints are behind the highest seen head slot.
+    Lag,
+    /// Endpoints disagree on the block at the same head slot.
+    Fork,
+    /// Not every endpoint has reported a head yet.
+    Unknown,
+}
+
+impl std::fmt::Display for HeadDivergence {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let s = match self {
+            HeadDivergence::None => "none",
+            HeadDivergence::Lag => "lag",
+            HeadDivergence::Fork => "fork",
+            HeadDivergence::Unknown => "unknown",
+        };
+        write!(f, "{}", s)
+    }
+}
+
+#[derive(Default)]
+struct HeadHistory {
+    /// The last head reported by the endpoint.
+    head: Option<Head>,
+    /// The head roots the endpoint has reported by slot.
+    roots: BTreeMap<u64, String>,
+}
+
+lazy_static! {
+    static ref HEADS: RwLock<HashMap<Url, HeadHistory>> = RwLock::new(HashMap::new());
+}
+
+/// Returns the last head reported by an endpoint.
+pub fn head(endpoint: &Url) -> Option<Head> {
+    HEADS
+        .read()
+        .unwrap()
+        .get(endpoint)
+        .and_then(|h| h.head.clone())
+}
+
+/// Drops the head history of an endpoint which is no longer checked.
+pub fn forget(endpoint: &Url) {
+    HEADS.write().unwrap().remove(endpoint);
+}
+
+/// Classifies how the heads of the given endpoints currently differ.
+pub fn classify(endpoints: &[Url]) -> HeadDivergence {
+    let heads: Option<Vec<Head>> = endpoints.iter().map(head).collect();
+    let heads = match heads {
+        Some(heads) if !heads.is_empty() => heads,
+        _ => return HeadDivergence::Unknown,
+    };
+    if heads.iter().any(|h| h.slot != heads[0].slot) {
+        return HeadDivergence::Lag;
+    }
+    if heads.iter().any(|h| h.root != heads[0].root) {
+        return HeadDivergence::Fork;
+    }
+    HeadDivergence::None
+}
+
+/// Polls the head of every endpoint on an interval, exporting how far
+/// each lags behind the highest head seen and counting reorgs.
+pub async fn run_head_monitor(endpoints: SharedEndpoints, every: Duration) {
+    info!("Starting head monitor");
+    let mut ticker = interval(every);
+    loop {
+        ticker.tick().await;
+        let polled = endpoints.read().unwrap().clone();
+        for u in polled.iter() {
+            let header: BeaconHeader = match crate::http::get(u, "eth/v1/beacon/headers/head").await
+            {
+                Ok(res) => res,
+                Err(e) => {
+                    warn!(
+                        "Request method=/eth/v1/beacon/headers/head endpoint={} failed {:?}",
+                        u, e
+                    );
+                    continue;
+                }
+            };
+            // The endpoint may have been removed while it was polled, and
+            // holding the lock keeps it from being removed while recording.
+            let current = endpoints.read().unwrap();
+            if !current.contains(u) {
+                continue;
+            }
+            let message = header.header.message;
+            record_head(
+                u,
+                Head {
+                    slot: message.slot,
+                    root: header.root,
+                },
+                &message.parent_root,
+            );
+        }
+        export_lag(&endpoints.read().unwrap());
+    }
+}
+
+fn record_head(u: &Url, head: Head, parent_root: &str) {
+    let mut heads = HEADS.write().unwrap();
+    let history = heads.entry(u.clone()).or_default();
+    if let Some(previous) = history.roots.get(&head.slot) {
+        if *previous != head.root {
+            crate::metrics::HEAD_REORGS_TOTAL
+                .with_label_values(&[u.as_str()])
+                .inc();
+            warn!(
+                "REORG DETECTED: endpoint={} slot={} old_root={} new_root={}",
+                u, head.slot, previous, head.root,
+            );
+        }
+    } else if let Some(parent_slot) = history
+        .roots
+        .iter()
+        .find(|(_, root)| root.as_str() == parent_root)
+        .map(|(slot, _)| *slot)
+    {
+        // A new head built on a block we saw orphans every block we saw
+        // after it, even when it moves to a slot not seen before. A parent
+        // we never saw is most likely a block between two polls.
+        if let Some((slot, orphaned)) = history.roots.range(parent_slot + 1..).next_back() {
+            crate::metrics::HEAD_REORGS_TOTAL
+                .with_label_values(&[u.as_str()])
+                .inc();
+            warn!(
+                "REORG DETECTED: endpoint={} slot={} orphaned_slot={} orphaned_root={} new_root={} parent_root={}",
+                u, head.slot, slot, orphaned, head.root, parent_root,
+            );
+        }
+    }
+    history.roots.insert(head.slot, head.root.clone());
+    // Drop history which is too old to be reorged.
+    let cutoff = head.slot.saturating_sub(HEAD_HISTORY_SLOTS);
+    history.roots = history.roots.split_off(&cutoff);
+    history.head = Some(head);
+}
+
+fn export_lag(endpoints: &[Url]) {
+    let heads: Vec<(Url, Head)> = endpoints
+        .iter()
+        .filter_map(|u| head(u).map(|h| (u.clone(), h)))
+        .collect();
+    let highest = match heads.iter().map(|(_, h)| h.slot).max() {
+        Some(slot) => slot,
+        None => return,
+    };
+    for (u, h) in heads.iter() {
+        let lag = highest - h.slot;
+        crate::metrics::HEAD_SLOT_LAG
+            .with_label_values(&[u.as_str()])
+            .set(lag as i64);
+        if lag > 0 {
+            info!("Head of endpoint={} lags by {} slots", u, lag);
+        }
+    }
+}
@@ -0,0 +1,351 @@
+use lazy_static::lazy_static;
+use serde::Serialize;
+use std::collections::{HashMap, VecDeque};
+use std::fmt::{Debug, Write};
+use std::sync::{Mutex, RwLock};
+use tokio::time::{Duration, Instant};
+use url::Url;
+
+/// How many runs of each check to keep.
+const MAX_CHECK_RUNS: usize = 50;
+
+/// How many latencies of each check against each endpoint to keep.
+const MAX_LATENCIES: usize = 60;
+
+/// How many mismatches to keep along with their diffs.
+const MAX_MISMATCHES: usize = 50;
+
+/// Responses are formatted up to this many bytes to find where they
+/// differ, as states can be hundreds of megabytes.
+const MAX_FORMATTED_LEN: usize = 1 << 20;
+
+/// How many differing lines of each response to keep.
+const MAX_DIFF_LINES: usize = 200;
+
+/// How many unchanged lines to keep around the lines which differ.
+const CONTEXT_LINES: usize = 3;
+
+/// The least time since the last pipeline run after which the checker
+/// is no longer considered ready, however short its interval.
+const MIN_READY_WINDOW: Duration = Duration::from_secs(60);
+
+/// A comparison of the responses of endpoints to a request of a check.
+#[derive(Debug, Clone, Serialize)]
+pub struct CheckRun {
+    /// When the comparison was made, in seconds since the unix epoch.
+    pub at: u64,
+    pub method: String,
+    pub succeeded: usize,
+    pub mismatched: bool,
+    /// The endpoints whose response differed from the majority.
+    pub divergent: Vec<String>,
+}
+
+/// The last result of a check against an endpoint.
+#[derive(Debug, Clone, Serialize)]
+pub struct LastResult {
+    pub at: u64,
+    pub method: String,
+    pub matched: bool,
+}
+
+/// A mismatch along with where the responses differ.
+#[derive(Debug, Clone, Serialize)]
+pub struct Mismatch {
+    pub at: u64,
+    pub check: String,
+    pub method: String,
+    pub divergent: Vec<String>,
+    /// The endpoint the others are diffed against, one which agreed with
+    /// the majority if there was one.
+    pub baseline: String,
+    /// The lines of each divergent endpoint's pretty printed response which
+    /// differ from the baseline's, tagged '-' if only in the baseline's and
+    /// '+' if only in the endpoint's, with a few lines of context.
+    pub diffs: Vec<(String, Vec<(char, String)>)>,
+}
+
+lazy_static! {
+    static ref CHECKS: Mutex<HashMap<String, VecDeque<CheckRun>>> = Mutex::new(HashMap::new());
+    static ref LAST_RESULTS: Mutex<HashMap<Url, HashMap<String, LastResult>>> =
+        Mutex::new(HashMap::new());
+    static ref MISMATCHES: Mutex<VecDeque<Mismatch>> = Mutex::new(VecDeque::new());
+    static ref LATENCIES: Mutex<HashMap<(String, Url), VecDeque<u64>>> = Mutex::new(HashMap::new());
+    static ref LAST_PIPELINE_RUN: RwLock<Option<(Instant, Duration)>> = RwLock::new(None);
+}
+
+/// Records the comparison of the responses of endpoints to a request, given
+/// in the same order as the endpoints which answered, with the indices of
+/// the endpoints which differed from the majority. The runs
+/// of triggered checks are only collected for whoever triggered them.
+pub fn record<T: Debug>(method: &str, endpoints: &[Url], responses: &[T], divergent: &[usize]) {
+    let at = crate::incidents::now();
+    let check = crate::incidents::route_of(method);
+    let divergent_endpoints: Vec<String> = divergent
+        .iter()
+        .filter_map(|i| endpoints.get(*i).map(|u| u.to_string()))
+        .collect();
+
+    let mut checks = CHECKS.lock().unwrap();
+    let run = CheckRun {
+        at,
+        method: method.to_string(),
+        succeeded: responses.len(),
+        mismatched: !divergent.is_empty(),
+        divergent: divergent_endpoints.clone(),
+    };
+    crate::control::collect(&run);
+    if crate::control::is_triggered() {
+        return;
+    }
+    let runs = checks.entry(check.clone()).or_default();
+    runs.push_front(run);
+    runs.truncate(MAX_CHECK_RUNS);
+
+    let mut last_results = LAST_RESULTS.lock().unwrap();
+    for (i, u) in endpoints.iter().enumerate().take(responses.len()) {
+        last_results.entry(u.clone()).or_default().insert(
+            check.clone(),
+            LastResult {
+                at,
+                method: method.to_string(),
+                matched: !divergent.contains(&i),
+            },
+        );
+    }
+
+    if divergent.is_empty() {
+        return;
+    }
+    // Without a majority every response is divergent, so the first one
+    // serves as the baseline.
+    let base = (0..responses.len())
+        .find(|i| !divergent.contains(i))
+        .unwrap_or(0);
+    let baseline = format_bounded(&responses[base]);
+    let diffs = divergent
+        .iter()
+        .filter(|i| **i != base)
+        .filter_map(|i| {
+            let u = endpoints.get(*i)?;
+            Some((
+                u.to_string(),
+                diff(&baseline, &format_bounded(&responses[*i])),
+            ))
+        })
+        .collect();
+    let mut mismatches = MISMATCHES.lock().unwrap();
+    mismatches.push_front(Mismatch {
+        at,
+        check,
+        method: method.to_string(),
+        divergent: divergent_endpoints,
+        baseline: endpoints
+            .get(base)
+            .map(|u| u.to_string())
+            .unwrap_or_default(),
+        diffs,
+    });
+    mismatches.truncate(MAX_MISMATCHES);
+}
+
+// A buffer which stops formatting once it holds `MAX_FORMATTED_LEN` bytes.
+struct Bounded(String);
+
+impl Write for Bounded {
+    fn write_str(&mut self, s: &str) -> std::fmt::Result {
+        let room = MAX_FORMATTED_LEN - self.0.len();
+        if s.len() <= room {
+            self.0.push_str(s);
+            return Ok(());
+        }
+        let mut end = room;
+        while !s.is_char_boundary(end) {
+            end -= 1;
+        }
+        self.0.push_str(&s[..end]);
+        Err(std::fmt::Error)
+    }
+}
+
+// Pretty prints a response, up to `MAX_FORMATTED_LEN` bytes of it.
+fn format_bounded<T: Debug>(response: &T) -> String {
+    let mut out = Bounded(String::new());
+    if write!(out, "{:#?}", response).is_err() {
+        out.0.push_str("\n...");
+    }
+    out.0
+}
+
+// Diffs two texts line by line, keeping the lines which differ with a few
+// lines of context. Leading and trailing lines they share are skipped
+// before diffing the rest, which would be too slow for whole states.
+fn diff(a: &str, b: &str) -> Vec<(char, String)> {
+    let a: Vec<&str> = a.lines().collect();
+    let b: Vec<&str> = b.lines().collect();
+    let prefix = a.iter().zip(b.iter()).take_while(|(x, y)| x == y).count();
+    let suffix = a[prefix..]
+        .iter()
+        .rev()
+        .zip(b[prefix..].iter().rev())
+        .take_while(|(x, y)| x == y)
+        .count();
+    let (a_rest, b_rest) = (&a[prefix..a.len() - suffix], &b[prefix..b.len() - suffix]);
+    let mut diff: Vec<(char, &str)> = a[prefix.saturating_sub(CONTEXT_LINES)..prefix]
+        .iter()
+        .map(|l| (' ', *l))
+        .collect();
+    if a_rest.len().max(b_rest.len()) <= MAX_DIFF_LINES {
+        diff.extend(line_diff(a_rest, b_rest));
+    } else {
+        let half = MAX_DIFF_LINES / 2;
+        diff.extend(a_rest.iter().take(half).map(|l| ('-', *l)));
+        diff.extend(b_rest.iter().take(half).map(|l| ('+', *l)));
+    }
+    diff.extend(
+        a[a.len() - suffix..]
+            .iter()
+            .take(CONTEXT_LINES)
+            .map(|l| (' ', *l)),
+    );
+    diff.into_iter().map(|(t, l)| (t, l.to_string())).collect()
+}
+
+// Diffs two lists of lines using their longest common subsequence, tagging
+// lines only in the first with '-' and only in the second with '+'.
+fn line_diff<'a>(a: &[&'a str], b: &[&'a str]) -> Vec<(char, &'a str)> {
+    let mut lcs = vec![vec![0usize; b.len() + 1]; a.len() + 1];
+    for i in (0..a.len()).rev() {
+        for j in (0..b.len()).rev() {
+            lcs[i][j] = if a[i] == b[j] {
+                lcs[i + 1][j + 1] + 1
+            } else {
+                lcs[i + 1][j].max(lcs[i][j + 1])
+            };
+        }
+    }
+    let (mut i, mut j) = (0, 0);
+    let mut diff = vec![];
+    while i < a.len() && j < b.len() {
+        if a[i] == b[j] {
+            diff.push((' ', a[i]));
+            i += 1;
+            j += 1;
+        } else if lcs[i + 1][j] >= lcs[i][j + 1] {
+            diff.push(('-', a[i]));
+            i += 1;
+        } else {
+            diff.push(('+', b[j]));
+            j += 1;
+        }
+    }
+    diff.extend(a[i..].iter().map(|l| ('-', *l)));
+    diff.extend(b[j..].iter().map(|l| ('+', *l)));
+    diff
+}
+
+/// Records the latency of a successful request to an endpoint, keyed by
+/// the route of the request like the runs of its check.
+pub fn observe(method: &str, endpoint: &Url, latency: Duration) {
+    if crate::control::is_triggered() {
+        return;
+    }
+    let check = crate::incidents::route_of(method);
+    let mut latencies = LATENCIES.lock().unwrap();
+    let samples = latencies.entry((check, endpoint.clone())).or_default();
+    samples.push_back(latency.as_millis() as u64);
+    while samples.len() > MAX_LATENCIES {
+        samples.pop_front();
+    }
+}
+
+/// Drops the results and latencies of an endpoint which is no longer
+/// checked. Runs and mismatches it took part in are kept.
+pub fn forget(endpoint: &Url) {
+    LAST_RESULTS.lock().unwrap().remove(endpoint);
+    LATENCIES.lock().unwrap().retain(|(_, u), _| u != endpoint);
+}
+
+/// Records that the pipeline finished a run.
+pub fn pipeline_ran(run_every: Duration) {
+    *LAST_PIPELINE_RUN.write().unwrap() = Some((Instant::now(), run_every));
+}
+
+/// Whether the pipeline finished a run recently enough for the checker to
+/// be doing its job.
+pub fn is_ready() -> bool {
+    match *LAST_PIPELINE_RUN.read().unwrap() {
+        Some((at, run_every)) => at.elapsed() < (run_every * 3).max(MIN_READY_WINDOW),
+        None => false,
+    }
+}
+
+/// The recent runs of every check, most recent first.
+pub fn checks() -> HashMap<String, Vec<CheckRun>> {
+    CHECKS
+        .lock()
+        .unwrap()
+        .iter()
+        .map(|(check, runs)| (check.clone(), runs.iter().cloned().collect()))
+        .collect()
+}
+
+/// The last result of every check against an endpoint.
+pub fn last_results(endpoint: &Url) -> HashMap<String, LastResult> {
+    LAST_RESULTS
+        .lock()
+        .unwrap()
+        .get(endpoint)
+        .cloned()
+        .unwrap_or_default()
+}
+
+/// The recent mismatches, most recent first.
+pub fn mismatches() -> Vec<Mismatch> {
+    MISMATCHES.lock().unwrap().iter().cloned().collect()
+}
+
+/// The recent latencies in millis of every check against every endpoint,
+/// oldest first.
+pub fn latencies() -> HashMap<(String, Url), Vec<u64>> {
+    LATENCIES
+        .lock()
+        .unwrap()
+        .iter()
+        .map(|(key, samples)| (key.clone(), samples.iter().copied().collect()))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_line_diff() {
+        let diff = line_diff(&["a", "b", "c"], &["a", "x", "c", "d"]);
+        assert_eq!(
+            diff,
+            vec![(' ', "a"), ('-', "b"), ('+', "x"), (' ', "c"), ('+', "d")]
+        );
+    }
+
+    #[test]
+    fn test_diff_keeps_context() {
+        let a = "1\n2\n3\n4\n5\nold\n6\n7\n8\n9";
+        let b = "1\n2\n3\n4\n5\nnew\n6\n7\n8\n9";
+        let diff: Vec<(char, &str)> = diff(a, b).iter().map(|(t, l)| (*t, l.as_str())).collect();
+        assert_eq!(
+            diff,
+            vec![
+                (' ', "3"),
+                (' ', "4"),
+                (' ', "5"),
+                ('-', "old"),
+                ('+', "new"),
+                (' ', "6"),
+                (' ', "7"),
+                (' ', "8")
+            ]
+        );
+    }
+}
@@ -0,0 +1,279 @@
+use lazy_static::lazy_static;
+use reqwest::{header::ACCEPT, StatusCode};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::RwLock;
+use std::time::Duration;
+use url::Url;
+
+/// The header beacon nodes use to report the fork of a versioned response.
+pub const CONSENSUS_VERSION_HEADER: &str = "Eth-Consensus-Version";
+
+/// Full states take far longer to serve than anything else, so their
+/// requests get at least this long whatever the request timeout.
+const MIN_STATE_TIMEOUT: Duration = Duration::from_secs(120);
+
+/// Block and state ids which are named rather than a slot or root.
+const NAMED_IDS: &[&str] = &["head", "finalized", "justified", "genesis"];
+
+lazy_static! {
+    static ref CLIENT: RwLock<(reqwest::Client, Duration)> =
+        RwLock::new(build_client(Duration::from_secs(10)));
+    static ref STREAM_CLIENT: RwLock<reqwest::Client> =
+        RwLock::new(build_stream_client(Duration::from_secs(10)));
+    static ref PROXIES: RwLock<HashMap<Url, Url>> = RwLock::new(HashMap::new());
+}
+
+fn build_client(timeout: Duration) -> (reqwest::Client, Duration) {
+    let client = reqwest::Client::builder()
+        .timeout(timeout)
+        .build()
+        .unwrap_or_default();
+    (client, timeout)
+}
+
+fn build_stream_client(timeout: Duration) -> reqwest::Client {
+    reqwest::Client::builder()
+        .connect_timeout(timeout)
+        .build()
+        .unwrap_or_default()
+}
+
+/// Sets how long any single request to an endpoint may take, so a node
+/// which never answers only fails its own requests.
+pub fn set_timeout(timeout: Duration) {
+    *CLIENT.write().unwrap() = build_client(timeout);
+    *STREAM_CLIENT.write().unwrap() = build_stream_client(timeout);
+}
+
+/// How long any single request to an endpoint may take.
+pub fn timeout() -> Duration {
+    CLIENT.read().unwrap().1
+}
+
+/// The client every request to an endpoint is sent with.
+pub fn client() -> reqwest::Client {
+    CLIENT.read().unwrap().0.clone()
+}
+
+/// The client event streams are opened with. Streams stay open for as long
+/// as the endpoint serves them, so only connecting is bounded by the timeout.
+pub fn stream_client() -> reqwest::Client {
+    STREAM_CLIENT.read().unwrap().clone()
+}
+
+/// Sends every request to an endpoint through a proxy, such as one which
+/// records or replays them. The endpoint is still reported by its own url.
+pub fn proxy(endpoint: Url, proxy: Url) {
+    PROXIES.write().unwrap().insert(endpoint, proxy);
+}
+
+/// The url requests to an endpoint are sent to.
+pub fn target(endpoint: &Url) -> Url {
+    PROXIES
+        .read()
+        .unwrap()
+        .get(endpoint)
+        .cloned()
+        .unwrap_or_else(|| endpoint.clone())
+}
+
+/// Whether a path segment is a block or state id rather than part of a route.
+pub fn is_id(segment: &str) -> bool {
+    NAMED_IDS.contains(&segment)
+        || segment.starts_with("0x")
+        || (!segment.is_empty() && segment.chars().all(|c| c.is_ascii_digit()))
+}
+
+/// A beacon_api_client for an endpoint which shares the request timeout.
+pub fn api_client(endpoint: &Url) -> beacon_api_client::Client {
+    beacon_api_client::Client::new_with_client(client(), target(endpoint))
+}
+
+// Requests for full states are allowed to take longer than the others.
+fn request(method: reqwest::Method, target: Url, path: &str) -> reqwest::RequestBuilder {
+    let (client, timeout) = CLIENT.read().unwrap().clone();
+    let request = client.request(method, target);
+    if path.contains("debug/beacon/states") {
+        return request.timeout(timeout.max(MIN_STATE_TIMEOUT));
+    }
+    request
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum Error {
+    #[error("got status {status}: {body}")]
+    Status { status: StatusCode, body: String },
+    #[error("{0}")]
+    Http(#[from] reqwest::Error),
+    #[error("{0}")]
+    Url(#[from] url::ParseError),
+    #[error("{0}")]
+    Json(#[from] serde_json::Error),
+}
+
+impl Error {
+    /// Whether the endpoint reported that it does not implement the route,
+    /// which is distinct from answering it incorrectly.
+    pub fn is_unsupported(&self) -> bool {
+        matches!(
+            self,
+            Error::Status { status, .. }
+                if *status == StatusCode::NOT_FOUND || *status == StatusCode::NOT_IMPLEMENTED
+        )
+    }
+}
+
+/// The envelope used by beacon API responses which wrap
+/// their payload in a `data` field.
+#[derive(Deserialize)]
+pub struct DataResponse<T> {
+    pub data: T,
+}
+
+/// The envelope used by beacon API responses which are versioned by fork.
+#[derive(Deserialize)]
+pub struct VersionedResponse<T> {
+    /// Missing from routes of objects which only exist from one fork on,
+    /// such as blob sidecars.
+    pub version: Option<String>,
+    pub data: T,
+}
+
+/// Performs a GET request against a beacon API endpoint for routes
+/// which are not exposed by beacon_api_client, returning the unwrapped
+/// `data` field of the response.
+pub async fn get<T: DeserializeOwned>(endpoint: &Url, path: &str) -> Result<T, Error> {
+    let res: DataResponse<T> = get_raw(endpoint, path).await?;
+    Ok(res.data)
+}
+
+/// Performs a GET request against a beacon API endpoint, returning the
+/// full response body for routes which do not use the `data` envelope.
+pub async fn get_raw<T: DeserializeOwned>(endpoint: &Url, path: &str) -> Result<T, Error> {
+    let target = target(endpoint).join(path)?;
+    let response = request(reqwest::Method::GET, target, path).send().await?;
+    let status = response.status();
+    if !status.is_success() {
+        let body = response.text().await.unwrap_or_default();
+        return Err(Error::Status { status, body });
+    }
+    let body: serde_json::Value = response.json().await?;
+    crate::openapi::check_response(endpoint, "get", path, status.as_u16(), &body);
+    Ok(serde_json::from_value(body)?)
+}
+
+/// Performs a GET request against a beacon API endpoint asking for the
+/// SSZ encoding of the response, returning the fork version from the
+/// Eth-Consensus-Version header along with the raw bytes.
+pub async fn get_ssz(endpoint: &Url, path: &str) -> Result<(Option<String>, Vec<u8>), Error> {
+    let target = target(endpoint).join(path)?;
+    let response = request(reqwest::Method::GET, target, path)
+        .header(ACCEPT, "application/octet-stream")
+        .send()
+        .await?;
+    let status = response.status();
+    if !status.is_success() {
+        let body = response.text().await.unwrap_or_default();
+        return Err(Error::Status { status, body });
+    }
+    let version = response
+        .headers()
+        .get(CONSENSUS_VERSION_HEADER)
+        .and_then(|v| v.to_str().ok())
+        .map(|v| v.to_lowercase());
+    let bytes = response.bytes().await?;
+    Ok((version, bytes.to_vec()))
+}
+
+/// The metadata beacon nodes attach to responses alongside their `data`,
+/// which beacon_api_client discards.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Metadata {
+    pub execution_optimistic: Option<bool>,
+    pub finalized: Option<bool>,
+    /// The fork reported in the `version` field of the body.
+    pub version: Option<String>,
+    /// The fork reported in the Eth-Consensus-Version header.
+    pub version_header: Option<String>,
+}
+
+/// Performs a GET request against a beacon API endpoint, returning only
+/// the metadata of the response.
+pub async fn get_metadata(endpoint: &Url, path: &str) -> Result<Metadata, Error> {
+    let target = target(endpoint).join(path)?;
+    let response = request(reqwest::Method::GET, target, path).send().await?;
+    let status = response.status();
+    if !status.is_success() {
+        let body = response.text().await.unwrap_or_default();
+        return Err(Error::Status { status, body });
+    }
+    let version_header = response
+        .headers()
+        .get(CONSENSUS_VERSION_HEADER)
+        .and_then(|v| v.to_str().ok())
+        .map(|v| v.to_lowercase());
+    let body: serde_json::Value = response.json().await?;
+    crate::openapi::check_response(endpoint, "get", path, status.as_u16(), &body);
+    Ok(Metadata {
+        execution_optimistic: body["execution_optimistic"].as_bool(),
+        finalized: body["finalized"].as_bool(),
+        version: body["version"].as_str().map(|v| v.to_lowercase()),
+        version_header,
+    })
+}
+
+/// Performs a GET request against a beacon API endpoint which only
+/// communicates through its status code, such as the node health route.
+pub async fn get_status(endpoint: &Url, path: &str) -> Result<StatusCode, Error> {
+    let target = target(endpoint).join(path)?;
+    let response = request(reqwest::Method::GET, target, path).send().await?;
+    Ok(response.status())
+}
+
+/// Performs a GET or, if a body is given, a JSON POST request against a
+/// beacon API endpoint, returning the status and body whatever the status.
+pub async fn send(
+    endpoint: &Url,
+    path: &str,
+    body: Option<&serde_json::Value>,
+) -> Result<(StatusCode, String), Error> {
+    let target = target(endpoint).join(path)?;
+    let (method, builder) = match body {
+        Some(body) => (
+            "post",
+            request(reqwest::Method::POST, target, path).json(body),
+        ),
+        None => ("get", request(reqwest::Method::GET, target, path)),
+    };
+    let response = builder.send().await?;
+    let status = response.status();
+    let body = response.text().await?;
+    if let Ok(json) = serde_json::from_str::<serde_json::Value>(&body) {
+        crate::openapi::check_response(endpoint, method, path, status.as_u16(), &json);
+    }
+    Ok((status, body))
+}
+
+/// Performs a POST request with a JSON body against a beacon API endpoint,
+/// returning the unwrapped `data` field of the response.
+pub async fn post<B: Serialize + ?Sized, T: DeserializeOwned>(
+    endpoint: &Url,
+    path: &str,
+    body: &B,
+) -> Result<T, Error> {
+    let target = target(endpoint).join(path)?;
+    let response = request(reqwest::Method::POST, target, path)
+        .json(body)
+        .send()
+        .await?;
+    let status = response.status();
+    if !status.is_success() {
+        let body = response.text().await.unwrap_or_default();
+        return Err(Error::Status { status, body });
+    }
+    let body: serde_json::Value = response.json().await?;
+    crate::openapi::check_response(endpoint, "post", path, status.as_u16(), &body);
+    let res: DataResponse<T> = serde_json::from_value(body)?;
+    Ok(res.data)
+}
@@ -0,0 +1,242 @@
+use axum::Json;
+use lazy_static::lazy_static;
+use serde::Serialize;
+use serde_json::{json, Value};
+use std::collections::{HashMap, VecDeque};
+use std::sync::{Mutex, RwLock};
+use std::time::{SystemTime, UNIX_EPOCH};
+use tracing::{info, warn};
+use url::Url;
+
+/// How many resolved incidents to keep around for the HTTP endpoint.
+const MAX_RESOLVED: usize = 100;
+
+/// The kind of failure an incident groups.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum IncidentKind {
+    /// An endpoint's response differed from the majority of endpoints.
+    Mismatch,
+    /// An endpoint failed its health check.
+    EndpointDown,
+    /// An endpoint breaches the latency objective of a check.
+    SloBreach,
+}
+
+impl IncidentKind {
+    pub fn name(&self) -> &'static str {
+        match self {
+            IncidentKind::Mismatch => "mismatch",
+            IncidentKind::EndpointDown => "endpoint_down",
+            IncidentKind::SloBreach => "slo_breach",
+        }
+    }
+}
+
+/// Repeated failures of the same kind of a check against an endpoint.
+#[derive(Debug, Clone, Serialize)]
+pub struct Incident {
+    pub kind: IncidentKind,
+    pub check: String,
+    pub endpoint: String,
+    /// When the incident opened and was last seen, in seconds since the unix epoch.
+    pub started_at: u64,
+    pub last_seen_at: u64,
+    pub resolved_at: Option<u64>,
+    pub occurrences: u64,
+    /// A description of the last failure, such as the request which mismatched.
+    pub last_sample: String,
+    /// How many runs in a row the failure did not occur.
+    #[serde(skip)]
+    clean_runs: usize,
+    /// Whether the incident was alerted on.
+    #[serde(skip)]
+    alerted: bool,
+}
+
+/// How many clean runs resolve an incident and how many occurrences of an
+/// endpoint being down open an alert for it.
+struct Settings {
+    resolve_after: usize,
+    failure_threshold: u64,
+}
+
+type IncidentKey = (IncidentKind, String, String);
+
+lazy_static! {
+    static ref SETTINGS: RwLock<Settings> = RwLock::new(Settings {
+        resolve_after: 3,
+        failure_threshold: 5,
+    });
+    static ref OPEN: Mutex<HashMap<IncidentKey, Incident>> = Mutex::new(HashMap::new());
+    static ref RESOLVED: Mutex<VecDeque<Incident>> = Mutex::new(VecDeque::new());
+}
+
+/// Sets how many clean runs resolve an incident and how many failed health
+/// checks in a row it takes to alert on an endpoint being down.
+pub fn configure(resolve_after: usize, failure_threshold: u64) {
+    *SETTINGS.write().unwrap() = Settings {
+        resolve_after: resolve_after.max(1),
+        failure_threshold: failure_threshold.max(1),
+    };
+}
+
+/// Records a failure, opening an incident for it or adding to the open one.
+pub fn record(kind: IncidentKind, check: &str, endpoint: &str, sample: String) {
+    let failure_threshold = SETTINGS.read().unwrap().failure_threshold;
+    let now = now();
+    let mut open = OPEN.lock().unwrap();
+    let incident = open
+        .entry((kind, check.to_string(), endpoint.to_string()))
+        .or_insert_with(|| {
+            crate::metrics::INCIDENTS_OPENED_TOTAL
+                .with_label_values(&[kind.name()])
+                .inc();
+            crate::metrics::INCIDENTS_OPEN
+                .with_label_values(&[kind.name(), check, endpoint])
+                .set(1);
+            warn!(
+                "INCIDENT OPENED: kind={} check={} endpoint={} sample={}",
+                kind.name(),
+                check,
+                endpoint,
+                sample
+            );
+            Incident {
+                kind,
+                check: check.to_string(),
+                endpoint: endpoint.to_string(),
+                started_at: now,
+                last_seen_at: now,
+                resolved_at: None,
+                occurrences: 0,
+                last_sample: String::new(),
+                clean_runs: 0,
+                alerted: false,
+            }
+        });
+    incident.occurrences += 1;
+    incident.last_seen_at = now;
+    incident.last_sample = sample;
+    incident.clean_runs = 0;
+    // A single failed health check is common enough to not be worth waking anyone.
+    let threshold = match kind {
+        IncidentKind::EndpointDown => failure_threshold,
+        _ => 1,
+    };
+    if !incident.alerted && incident.occurrences >= threshold {
+        incident.alerted = true;
+        crate::alerts::notify(incident);
+    }
+}
+
+/// Records a run in which a failure did not occur, resolving its incident
+/// after enough clean runs in a row.
+pub fn clean(kind: IncidentKind, check: &str, endpoint: &str) {
+    let resolve_after = SETTINGS.read().unwrap().resolve_after;
+    let key = (kind, check.to_string(), endpoint.to_string());
+    let mut open = OPEN.lock().unwrap();
+    let incident = match open.get_mut(&key) {
+        Some(incident) => incident,
+        None => return,
+    };
+    incident.clean_runs += 1;
+    if incident.clean_runs < resolve_after {
+        return;
+    }
+    resolve(open.remove(&key).unwrap());
+}
+
+/// Resolves every open incident of an endpoint which is no longer checked.
+pub fn forget(endpoint: &str) {
+    let mut open = OPEN.lock().unwrap();
+    let keys: Vec<IncidentKey> = open
+        .keys()
+        .filter(|(_, _, e)| e == endpoint)
+        .cloned()
+        .collect();
+    for key in keys {
+        resolve(open.remove(&key).unwrap());
+    }
+}
+
+fn resolve(mut incident: Incident) {
+    incident.resolved_at = Some(now());
+    let _ = crate::metrics::INCIDENTS_OPEN.remove_label_values(&[
+        incident.kind.name(),
+        &incident.check,
+        &incident.endpoint,
+    ]);
+    info!(
+        "Incident resolved: kind={} check={} endpoint={} occurrences={}",
+        incident.kind.name(),
+        incident.check,
+        incident.endpoint,
+        incident.occurrences
+    );
+    if incident.alerted {
+        crate::alerts::notify(&incident);
+    }
+    let mut resolved = RESOLVED.lock().unwrap();
+    resolved.push_front(incident);
+    resolved.truncate(MAX_RESOLVED);
+}
+
+/// Records the outcome of comparing the responses of the endpoints which
+/// answered a request, with the indices of the endpoints which differed from
+/// the majority. Endpoints which did not answer are left as they were. The
+/// request is grouped with others of its route whatever ids it used.
+pub fn comparison(method: &str, endpoints: &[Url], divergent: &[usize]) {
+    // Triggered runs use whatever parameters were asked for, so they
+    // neither open nor resolve the incidents of the scheduled runs.
+    if crate::control::is_triggered() {
+        return;
+    }
+    let check = route_of(method);
+    for (i, u) in endpoints.iter().enumerate() {
+        if divergent.contains(&i) {
+            record(
+                IncidentKind::Mismatch,
+                &check,
+                u.as_str(),
+                method.to_string(),
+            );
+        } else {
+            clean(IncidentKind::Mismatch, &check, u.as_str());
+        }
+    }
+}
+
+// Replaces the ids in a request path with a placeholder, so incidents are
+// grouped by route rather than by the random ids each run picks.
+pub(crate) fn route_of(method: &str) -> String {
+    method
+        .split('?')
+        .next()
+        .unwrap_or_default()
+        .split('/')
+        .map(|s| if crate::http::is_id(s) { "{id}" } else { s })
+        .collect::<Vec<&str>>()
+        .join("/")
+}
+
+/// The open incidents, oldest first.
+pub fn open() -> Vec<Incident> {
+    let mut open: Vec<Incident> = OPEN.lock().unwrap().values().cloned().collect();
+    open.sort_by_key(|i| i.started_at);
+    open
+}
+
+/// Serves the open and most recently resolved incidents.
+pub async fn handler() -> Json<Value> {
+    let resolved: Vec<Incident> = RESOLVED.lock().unwrap().iter().cloned().collect();
+    Json(json!({ "open": open(), "resolved": resolved }))
+}
+
+/// The current time in seconds since the unix epoch.
+pub(crate) fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or_default()
+}
@@ -1,21 +1,44 @@
-use axum::{routing::get, Router};
-use clap::Parser;
+use axum::{
+    routing::{get, post},
+    Router,
+};
+use clap::{Parser, Subcommand};
 use eyre::Result;
 use futures::future::join_all;
+use std::path::PathBuf;
+use tokio::sync::watch;
 use tokio::time::{interval, Duration};
 use tracing::{info, Level};
 use tracing_subscriber::FmtSubscriber;
 use url::Url;
 
+mod alerts;
+mod api;
 mod api_checker;
+mod bench;
+mod cassette;
+mod control;
+mod dashboard;
+mod encoding;
 mod endpoints;
+mod events;
+mod head_monitor;
+mod history;
+mod http;
+mod incidents;
 mod metrics;
+#[cfg(test)]
+mod mock;
+mod node_status;
+mod openapi;
+mod settings;
+mod slo;
+mod state_diff;
 mod types;
 
-use crate::api_checker::{force_boxed, ApiChecker, CheckerFn};
-use crate::endpoints::{
-    check_balances, check_block, check_finality_checkpoints, check_state_root, check_validators,
-};
+use crate::api_checker::ApiChecker;
+use crate::encoding::EncodingMode;
+use crate::settings::Settings;
 
 #[derive(Parser, Debug)]
 #[command(name = "beacon-api-checker")]
@@ -26,6 +49,8 @@ use crate::endpoints::{
     long_about = None,
 )]
 struct Cli {
+    #[command(subcommand)]
+    command: Option<Command>,
     #[arg(long)]
     endpoint: Vec<String>,
     #[arg(default_value = "127.0.0.1")]
@@ -36,6 +61,99 @@ struct Cli {
     interval_millis: Option<Duration>,
     #[arg(value_parser = parse_duration)]
     http_timeout: Option<Duration>,
+    /// How often to poll the node health, syncing and peer routes, in millis.
+    #[arg(long, value_parser = parse_duration, default_value = "12000")]
+    node_status_interval: Duration,
+    /// How often to poll the head of every endpoint, in millis.
+    #[arg(long, value_parser = parse_duration, default_value = "4000")]
+    head_monitor_interval: Duration,
+    /// Subscribe to /eth/v1/events on every endpoint and compare their event streams.
+    #[arg(long)]
+    subscribe_events: bool,
+    /// How long after the first endpoint an event may arrive before it is late, in millis.
+    #[arg(long, value_parser = parse_duration, default_value = "4000")]
+    event_late_after: Duration,
+    /// How long after the first endpoint an event may arrive before it is missing, in millis.
+    #[arg(long, value_parser = parse_duration, default_value = "12000")]
+    event_missing_after: Duration,
+    /// Which encodings to request for blocks, states and blob sidecars.
+    #[arg(long, value_enum, default_value_t = EncodingMode::Json)]
+    encoding: EncodingMode,
+    /// Download and diff full states from the debug API every run, rather
+    /// than only when state roots mismatch.
+    #[arg(long)]
+    debug_state: bool,
+    /// Compute hash tree roots of served blocks and states locally and
+    /// verify them against the roots each node reports.
+    #[arg(long)]
+    verify_roots: bool,
+    /// Send deliberately invalid requests and verify nodes answer them as specified.
+    #[arg(long)]
+    error_paths: bool,
+    /// Path to a bundled beacon-APIs OpenAPI document, in JSON or YAML, to
+    /// validate every node's responses against.
+    #[arg(long)]
+    openapi_spec: Option<PathBuf>,
+    /// Request every GET route of the OpenAPI spec which parameters can be
+    /// synthesized for and compare the raw responses across nodes.
+    #[arg(long, requires = "openapi_spec")]
+    openapi_coverage: bool,
+    /// Record every request to the endpoints and its response to a cassette file.
+    #[arg(long, conflicts_with = "playback")]
+    record: Option<PathBuf>,
+    /// Check endpoints replayed from a cassette file instead of the given endpoints.
+    #[arg(long)]
+    playback: Option<PathBuf>,
+    /// Path to a YAML or JSON file of per-check latency objectives to track per endpoint.
+    #[arg(long)]
+    slo_config: Option<PathBuf>,
+    /// How often to compute compliance with the latency objectives, in millis.
+    #[arg(long, value_parser = parse_duration, default_value = "10000")]
+    slo_interval: Duration,
+    /// Webhooks to POST alerts to as JSON.
+    #[arg(long)]
+    alert_webhook: Vec<Url>,
+    /// Slack incoming webhooks to post alerts to.
+    #[arg(long)]
+    alert_slack: Vec<Url>,
+    /// Discord channel webhooks to post alerts to.
+    #[arg(long)]
+    alert_discord: Vec<Url>,
+    /// The least time between two deliveries of alerts, in millis. Alerts
+    /// raised in between are batched into the next delivery.
+    #[arg(long, value_parser = parse_duration, default_value = "60000")]
+    alert_min_interval: Duration,
+    /// How many clean runs in a row resolve an incident.
+    #[arg(long, default_value_t = 3)]
+    incident_resolve_after: usize,
+    /// How many health checks in a row an endpoint must fail before it is alerted as down.
+    #[arg(long, default_value_t = 5)]
+    alert_failure_threshold: u64,
+    /// Leave out endpoints which report themselves as syncing from the checks.
+    #[arg(long)]
+    skip_syncing: bool,
+    /// Path to a YAML or JSON file of endpoints, interval_millis and checks
+    /// which override the command line and are reloaded when the file
+    /// changes or on SIGHUP.
+    #[arg(long, conflicts_with_all = ["record", "playback"])]
+    settings: Option<PathBuf>,
+    /// How often to check the settings file for changes, in millis.
+    #[arg(long, value_parser = parse_duration, default_value = "5000")]
+    settings_poll_interval: Duration,
+    /// Bearer token enabling the /control routes, which pause and resume
+    /// the checks, trigger a check and add or remove endpoints.
+    #[arg(long)]
+    control_token: Option<String>,
+    /// How long a check triggered through the control API may take, in
+    /// millis. Each of its requests is also bounded by the HTTP timeout.
+    #[arg(long, value_parser = parse_duration, default_value = "600000")]
+    trigger_timeout: Duration,
+}
+
+#[derive(Subcommand, Debug)]
+enum Command {
+    /// Load test the endpoints instead of checking them.
+    Bench(crate::bench::BenchArgs),
 }
 
 #[tokio::main]
@@ -52,35 +170,163 @@ async fn main() -> Result<()> {
     // Parses the list of specified beacon API endpoints.
     let endpoints: Result<Vec<Url>, _> = cli.endpoint.into_iter().map(|e| Url::parse(&e)).collect();
 
-    // Defines a pipeline of functions to run through our API checker.
+    // Defines a pipeline of checks, by name, to run through our API checker.
     // Each function will call a respective API endpoint across all specified
     // beacon node URLs and cross-check their responses.
     // At this time, the pipeline is executed sequentially.
-    let pipeline: Vec<CheckerFn> = vec![
-        force_boxed(check_validators),
-        force_boxed(check_balances),
-        force_boxed(check_block),
-        force_boxed(check_finality_checkpoints),
-        force_boxed(check_state_root),
+    let mut checks = vec![
+        "validators",
+        "balances",
+        "block",
+        "finality_checkpoints",
+        "state_root",
+        "proposer_duties",
+        "attester_duties",
+        "sync_committee_duties",
+        "block_rewards",
+        "attestation_rewards",
+        "sync_committee_rewards",
+        "light_client_bootstrap",
+        "light_client_updates",
+        "light_client_finality_update",
+        "light_client_optimistic_update",
+        "blob_sidecars",
+        "block_attestations",
+        "pool_attestations",
+        "pool_attester_slashings",
+        "pool_proposer_slashings",
+        "pool_voluntary_exits",
+        "pool_bls_to_execution_changes",
+        "metadata",
     ];
+    if let Some(path) = &cli.openapi_spec {
+        crate::openapi::load(path)?;
+        checks.push("response_schemas");
+    }
+    if cli.openapi_coverage {
+        checks.push("openapi_routes");
+    }
+    if cli.error_paths {
+        checks.push("error_paths");
+    }
+    if cli.verify_roots {
+        checks.push("block_hash_tree_root");
+        checks.push("state_hash_tree_root");
+    }
+    if cli.debug_state {
+        checks.push("debug_state");
+    }
+    match cli.encoding {
+        EncodingMode::Json => {}
+        EncodingMode::Both => {
+            checks.push("block_encodings");
+            checks.push("state_encodings");
+            checks.push("blob_sidecar_encodings");
+        }
+        EncodingMode::Ssz => {
+            checks.push("block_ssz");
+            checks.push("state_ssz");
+            checks.push("blob_sidecars_ssz");
+        }
+    }
 
     // Builds an API checker from our specified CLI flags
     // and the pipeline defined above.
+    let mut endpoints = endpoints.unwrap();
+    if let Some(path) = &cli.playback {
+        endpoints = crate::cassette::playback(path).await?;
+    } else if let Some(path) = &cli.record {
+        crate::cassette::record(endpoints.clone(), path).await?;
+    }
+    if let Some(Command::Bench(args)) = cli.command {
+        return crate::bench::run(endpoints, args).await;
+    }
+    // The settings file, if any, overrides the endpoints, interval and
+    // checks given on the command line, both now and when it is reloaded.
+    let defaults = Settings {
+        endpoints: Some(endpoints),
+        interval_millis: Some(
+            cli.interval_millis
+                .unwrap_or(ApiChecker::default().run_every)
+                .as_millis() as u64,
+        ),
+        checks: Some(checks.iter().map(|c| c.to_string()).collect()),
+    };
+    let settings = match &cli.settings {
+        Some(path) => crate::settings::load(path)?,
+        None => Settings::default(),
+    };
+    let (settings_sender, settings_receiver) = watch::channel(settings.clone());
+    let initial = settings.or(&defaults);
     let mut api_checker = ApiChecker::new()
-        .endpoints(endpoints.unwrap())
-        .pipeline(pipeline);
+        .endpoints(initial.endpoints.clone().unwrap_or_default())
+        .pipeline(initial.pipeline())
+        .skip_syncing(cli.skip_syncing);
 
     if cli.http_timeout.is_some() {
         api_checker = api_checker.timeout(cli.http_timeout.unwrap());
     }
-    if cli.interval_millis.is_some() {
-        api_checker = api_checker.run_every(cli.interval_millis.unwrap());
+    if let Some(millis) = initial.interval_millis {
+        api_checker = api_checker.run_every(Duration::from_millis(millis));
     }
     api_checker = api_checker.build();
+    let endpoints = api_checker.shared_endpoints();
+    crate::control::configure(cli.control_token, endpoints.clone(), cli.trigger_timeout);
 
     // Rnus our API checker and metrics server in the background.
     let mut handles = vec![];
-    handles.push(tokio::spawn(run_api_checker(api_checker)));
+    handles.push(tokio::spawn(run_api_checker(
+        api_checker,
+        defaults,
+        settings_receiver,
+    )));
+    if let Some(path) = cli.settings {
+        handles.push(tokio::spawn(crate::settings::watch(
+            path,
+            cli.settings_poll_interval,
+            settings_sender,
+        )));
+    }
+    handles.push(tokio::spawn(crate::node_status::run_node_status(
+        endpoints.clone(),
+        cli.node_status_interval,
+    )));
+    handles.push(tokio::spawn(crate::head_monitor::run_head_monitor(
+        endpoints.clone(),
+        cli.head_monitor_interval,
+    )));
+    crate::incidents::configure(cli.incident_resolve_after, cli.alert_failure_threshold);
+    let sinks: Vec<crate::alerts::Sink> = cli
+        .alert_webhook
+        .into_iter()
+        .map(crate::alerts::Sink::Webhook)
+        .chain(cli.alert_slack.into_iter().map(crate::alerts::Sink::Slack))
+        .chain(
+            cli.alert_discord
+                .into_iter()
+                .map(crate::alerts::Sink::Discord),
+        )
+        .collect();
+    if !sinks.is_empty() {
+        handles.push(tokio::spawn(crate::alerts::run_alerts(
+            sinks,
+            cli.alert_min_interval,
+        )));
+    }
+    if let Some(path) = &cli.slo_config {
+        crate::slo::load(path)?;
+        handles.push(tokio::spawn(crate::slo::run_slo_monitor(cli.slo_interval)));
+    }
+    if cli.subscribe_events {
+        // Event streams are subscribed to once, so endpoints added at
+        // runtime are not streamed from.
+        let endpoints = endpoints.read().unwrap().clone();
+        handles.push(tokio::spawn(crate::events::run_event_monitor(
+            endpoints,
+            cli.event_late_after,
+            cli.event_missing_after,
+        )));
+    }
 
     let metrics_server = setup_metrics_server(cli.metrics_host, cli.metrics_port);
     handles.push(tokio::spawn(metrics_server));
@@ -91,19 +337,54 @@ async fn main() -> Result<()> {
 
 pub async fn setup_metrics_server(host: String, port: u32) {
     info!("Starting prometheus metrics server");
-    let router = Router::new().route("/metrics", get(crate::metrics::handler));
+    let router = Router::new()
+        .route("/metrics", get(crate::metrics::handler))
+        .route("/incidents", get(crate::incidents::handler))
+        .route("/status", get(crate::api::status))
+        .route("/checks", get(crate::api::checks))
+        .route("/mismatches", get(crate::api::mismatches))
+        .route("/healthz", get(crate::api::healthz))
+        .route("/readyz", get(crate::api::readyz))
+        .route("/dashboard", get(crate::dashboard::handler))
+        .route("/control/pause", post(crate::control::pause))
+        .route("/control/resume", post(crate::control::resume))
+        .route("/control/trigger", post(crate::control::trigger))
+        .route(
+            "/control/endpoints",
+            get(crate::control::list_endpoints)
+                .post(crate::control::add_endpoint)
+                .delete(crate::control::remove_endpoint),
+        );
     let addr = format!("{}:{}", host, port);
     let server = axum::Server::bind(&addr.parse().unwrap()).serve(router.into_make_service());
     server.await.unwrap();
 }
 
-pub async fn run_api_checker(checker: ApiChecker) {
+pub async fn run_api_checker(
+    mut checker: ApiChecker,
+    defaults: Settings,
+    mut reloads: watch::Receiver<Settings>,
+) {
     info!("Starting API checker");
+    let mut current = reloads.borrow().or(&defaults);
     let mut ticker = interval(checker.run_every);
     loop {
-        ticker.tick().await;
+        tokio::select! {
+            _ = ticker.tick() => {}
+            Ok(()) = reloads.changed() => {
+                let next = reloads.borrow_and_update().or(&defaults);
+                next.apply(&current, &mut checker);
+                current = next;
+                ticker = interval(checker.run_every);
+                continue;
+            }
+        }
+        if crate::control::is_paused() {
+            continue;
+        }
         info!("Running API checker pipeline");
         checker.run_pipeline().await.unwrap();
+        crate::history::pipeline_ran(checker.run_every);
     }
 }
 
@@ -1,8 +1,15 @@
 use axum::http::StatusCode;
-use prometheus::{self, Histogram, IntCounter, TextEncoder};
+use prometheus::core::{Collector, MetricVec, MetricVecBuilder};
+use prometheus::{
+    self, GaugeVec, Histogram, HistogramVec, IntCounter, IntCounterVec, IntGaugeVec, TextEncoder,
+};
 
 use lazy_static::lazy_static;
-use prometheus::{register_histogram, register_int_counter};
+use prometheus::{
+    register_gauge_vec, register_histogram, register_histogram_vec, register_int_counter,
+    register_int_counter_vec, register_int_gauge_vec,
+};
+use std::collections::HashMap;
 
 const HTTP_RESPONSE_TIME_CUSTOM_BUCKETS: &[f64; 12] = &[
     10.0, 50.0, 100.0, 150.0, 200.0, 300.0, 500.0, 1000.0, 5000.0, 10000.0, 30000.0, 60000.0,
@@ -35,6 +42,147 @@ lazy_static! {
         "Mismatched get_state_root responses",
     )
     .unwrap();
+    pub static ref PROPOSER_DUTIES_NOT_EQUAL_TOTAL: IntCounter = register_int_counter!(
+        "api_checker_get_proposer_duties_unequal_total",
+        "Mismatched get_proposer_duties responses",
+    )
+    .unwrap();
+    pub static ref ATTESTER_DUTIES_NOT_EQUAL_TOTAL: IntCounter = register_int_counter!(
+        "api_checker_get_attester_duties_unequal_total",
+        "Mismatched get_attester_duties responses",
+    )
+    .unwrap();
+    pub static ref SYNC_COMMITTEE_DUTIES_NOT_EQUAL_TOTAL: IntCounter = register_int_counter!(
+        "api_checker_get_sync_committee_duties_unequal_total",
+        "Mismatched get_sync_committee_duties responses",
+    )
+    .unwrap();
+    pub static ref BLOCK_REWARDS_NOT_EQUAL_TOTAL: IntCounter = register_int_counter!(
+        "api_checker_get_block_rewards_unequal_total",
+        "Mismatched get_block_rewards responses",
+    )
+    .unwrap();
+    pub static ref ATTESTATION_REWARDS_NOT_EQUAL_TOTAL: IntCounter = register_int_counter!(
+        "api_checker_get_attestation_rewards_unequal_total",
+        "Mismatched get_attestation_rewards responses",
+    )
+    .unwrap();
+    pub static ref SYNC_COMMITTEE_REWARDS_NOT_EQUAL_TOTAL: IntCounter = register_int_counter!(
+        "api_checker_get_sync_committee_rewards_unequal_total",
+        "Mismatched get_sync_committee_rewards responses",
+    )
+    .unwrap();
+    pub static ref REWARDS_FIELD_NOT_EQUAL_TOTAL: IntCounterVec = register_int_counter_vec!(
+        "api_checker_rewards_field_unequal_total",
+        "Mismatched fields across rewards responses by rewards kind and field",
+        &["kind", "field"],
+    )
+    .unwrap();
+    pub static ref LIGHT_CLIENT_NOT_EQUAL_TOTAL: IntCounterVec = register_int_counter_vec!(
+        "api_checker_light_client_unequal_total",
+        "Mismatched light client responses by route",
+        &["route"],
+    )
+    .unwrap();
+    pub static ref LIGHT_CLIENT_UNSUPPORTED_TOTAL: IntCounterVec = register_int_counter_vec!(
+        "api_checker_light_client_unsupported_total",
+        "Light client requests answered with 404 or 501 by route and endpoint",
+        &["route", "endpoint"],
+    )
+    .unwrap();
+    pub static ref BLOB_SIDECARS_NOT_EQUAL_TOTAL: IntCounter = register_int_counter!(
+        "api_checker_get_blob_sidecars_unequal_total",
+        "Mismatched get_blob_sidecars responses",
+    )
+    .unwrap();
+    pub static ref BLOB_COMMITMENTS_INCONSISTENT_TOTAL: IntCounterVec = register_int_counter_vec!(
+        "api_checker_blob_commitments_inconsistent_total",
+        "Blob sidecars whose KZG commitment differs from the block body by endpoint",
+        &["endpoint"],
+    )
+    .unwrap();
+    pub static ref BLOCK_ATTESTATIONS_NOT_EQUAL_TOTAL: IntCounter = register_int_counter!(
+        "api_checker_get_block_attestations_unequal_total",
+        "Mismatched get_block_attestations responses",
+    )
+    .unwrap();
+    pub static ref HEAD_BLOCK_NOT_EQUAL_TOTAL: IntCounterVec = register_int_counter_vec!(
+        "api_checker_get_head_block_unequal_total",
+        "Mismatched get_beacon_block responses for the head by head divergence",
+        &["divergence"],
+    )
+    .unwrap();
+    pub static ref SSZ_NOT_EQUAL_TOTAL: IntCounterVec = register_int_counter_vec!(
+        "api_checker_ssz_unequal_total",
+        "Mismatched SSZ responses by object",
+        &["object"],
+    )
+    .unwrap();
+    pub static ref ENCODINGS_INCONSISTENT_TOTAL: IntCounterVec = register_int_counter_vec!(
+        "api_checker_encodings_inconsistent_total",
+        "Responses whose JSON and SSZ encodings disagree by object and endpoint",
+        &["object", "endpoint"],
+    )
+    .unwrap();
+    pub static ref DEBUG_STATE_NOT_EQUAL_TOTAL: IntCounter = register_int_counter!(
+        "api_checker_get_debug_state_unequal_total",
+        "Mismatched get_debug_state responses",
+    )
+    .unwrap();
+    pub static ref COMPUTED_ROOT_NOT_EQUAL_TOTAL: IntCounterVec = register_int_counter_vec!(
+        "api_checker_computed_root_unequal_total",
+        "Mismatched locally computed hash tree roots across endpoints by object",
+        &["object"],
+    )
+    .unwrap();
+    pub static ref SELF_INCONSISTENT_TOTAL: IntCounterVec = register_int_counter_vec!(
+        "api_checker_self_inconsistent_total",
+        "Roots reported by an endpoint which differ from the root computed over the object it serves",
+        &["object", "source", "endpoint"],
+    )
+    .unwrap();
+    pub static ref METADATA_NOT_EQUAL_TOTAL: IntCounterVec = register_int_counter_vec!(
+        "api_checker_metadata_unequal_total",
+        "Mismatched execution_optimistic, finalized or version metadata by route",
+        &["route"],
+    )
+    .unwrap();
+    pub static ref METADATA_INCONSISTENT_TOTAL: IntCounterVec = register_int_counter_vec!(
+        "api_checker_metadata_inconsistent_total",
+        "Response metadata contradicting the request or itself by route, reason and endpoint",
+        &["route", "reason", "endpoint"],
+    )
+    .unwrap();
+    pub static ref ERROR_PATH_NOT_EQUAL_TOTAL: IntCounterVec = register_int_counter_vec!(
+        "api_checker_error_path_unequal_total",
+        "Mismatched statuses across endpoints for invalid requests by case",
+        &["case"],
+    )
+    .unwrap();
+    pub static ref ERROR_PATH_NONCONFORMANT_TOTAL: IntCounterVec = register_int_counter_vec!(
+        "api_checker_error_path_nonconformant_total",
+        "Answers to invalid requests which deviate from the spec by case, reason and endpoint",
+        &["case", "reason", "endpoint"],
+    )
+    .unwrap();
+    pub static ref SCHEMA_VIOLATIONS_TOTAL: IntCounterVec = register_int_counter_vec!(
+        "api_checker_schema_violations_total",
+        "Violations of the OpenAPI response schema by path template and endpoint",
+        &["route", "endpoint"],
+    )
+    .unwrap();
+    pub static ref OPENAPI_NOT_EQUAL_TOTAL: IntCounterVec = register_int_counter_vec!(
+        "api_checker_openapi_unequal_total",
+        "Mismatched responses to requests synthesized from the OpenAPI spec by path template",
+        &["route"],
+    )
+    .unwrap();
+    pub static ref OPENAPI_UNSUPPORTED_TOTAL: IntCounterVec = register_int_counter_vec!(
+        "api_checker_openapi_unsupported_total",
+        "Requests synthesized from the OpenAPI spec answered with 404 or 501 by path template and endpoint",
+        &["route", "endpoint"],
+    )
+    .unwrap();
 
     // Latency metrics.
     pub static ref GET_VALIDATORS_LATENCY_MILLISECONDS: Histogram = register_histogram!(
@@ -62,6 +210,254 @@ lazy_static! {
         "Median latency of API responses for /eth/v1/beacon/states/root in millis",
         HTTP_RESPONSE_TIME_CUSTOM_BUCKETS.to_vec(),
     ).unwrap();
+    pub static ref GET_PROPOSER_DUTIES_LATENCY_MILLISECONDS: Histogram = register_histogram!(
+        "api_checker_get_proposer_duties_latency_milliseconds",
+        "Median latency of API responses for /eth/v1/validator/duties/proposer in millis",
+        HTTP_RESPONSE_TIME_CUSTOM_BUCKETS.to_vec(),
+    ).unwrap();
+    pub static ref GET_ATTESTER_DUTIES_LATENCY_MILLISECONDS: Histogram = register_histogram!(
+        "api_checker_get_attester_duties_latency_milliseconds",
+        "Median latency of API responses for /eth/v1/validator/duties/attester in millis",
+        HTTP_RESPONSE_TIME_CUSTOM_BUCKETS.to_vec(),
+    ).unwrap();
+    pub static ref GET_SYNC_COMMITTEE_DUTIES_LATENCY_MILLISECONDS: Histogram = register_histogram!(
+        "api_checker_get_sync_committee_duties_latency_milliseconds",
+        "Median latency of API responses for /eth/v1/validator/duties/sync in millis",
+        HTTP_RESPONSE_TIME_CUSTOM_BUCKETS.to_vec(),
+    ).unwrap();
+    pub static ref GET_BLOCK_REWARDS_LATENCY_MILLISECONDS: Histogram = register_histogram!(
+        "api_checker_get_block_rewards_latency_milliseconds",
+        "Median latency of API responses for /eth/v1/beacon/rewards/blocks in millis",
+        HTTP_RESPONSE_TIME_CUSTOM_BUCKETS.to_vec(),
+    ).unwrap();
+    pub static ref GET_ATTESTATION_REWARDS_LATENCY_MILLISECONDS: Histogram = register_histogram!(
+        "api_checker_get_attestation_rewards_latency_milliseconds",
+        "Median latency of API responses for /eth/v1/beacon/rewards/attestations in millis",
+        HTTP_RESPONSE_TIME_CUSTOM_BUCKETS.to_vec(),
+    ).unwrap();
+    pub static ref GET_SYNC_COMMITTEE_REWARDS_LATENCY_MILLISECONDS: Histogram = register_histogram!(
+        "api_checker_get_sync_committee_rewards_latency_milliseconds",
+        "Median latency of API responses for /eth/v1/beacon/rewards/sync_committee in millis",
+        HTTP_RESPONSE_TIME_CUSTOM_BUCKETS.to_vec(),
+    ).unwrap();
+    pub static ref GET_LIGHT_CLIENT_BOOTSTRAP_LATENCY_MILLISECONDS: Histogram = register_histogram!(
+        "api_checker_get_light_client_bootstrap_latency_milliseconds",
+        "Median latency of API responses for /eth/v1/beacon/light_client/bootstrap in millis",
+        HTTP_RESPONSE_TIME_CUSTOM_BUCKETS.to_vec(),
+    ).unwrap();
+    pub static ref GET_LIGHT_CLIENT_UPDATES_LATENCY_MILLISECONDS: Histogram = register_histogram!(
+        "api_checker_get_light_client_updates_latency_milliseconds",
+        "Median latency of API responses for /eth/v1/beacon/light_client/updates in millis",
+        HTTP_RESPONSE_TIME_CUSTOM_BUCKETS.to_vec(),
+    ).unwrap();
+    pub static ref GET_LIGHT_CLIENT_FINALITY_UPDATE_LATENCY_MILLISECONDS: Histogram = register_histogram!(
+        "api_checker_get_light_client_finality_update_latency_milliseconds",
+        "Median latency of API responses for /eth/v1/beacon/light_client/finality_update in millis",
+        HTTP_RESPONSE_TIME_CUSTOM_BUCKETS.to_vec(),
+    ).unwrap();
+    pub static ref GET_LIGHT_CLIENT_OPTIMISTIC_UPDATE_LATENCY_MILLISECONDS: Histogram = register_histogram!(
+        "api_checker_get_light_client_optimistic_update_latency_milliseconds",
+        "Median latency of API responses for /eth/v1/beacon/light_client/optimistic_update in millis",
+        HTTP_RESPONSE_TIME_CUSTOM_BUCKETS.to_vec(),
+    ).unwrap();
+    pub static ref GET_BLOB_SIDECARS_LATENCY_MILLISECONDS: Histogram = register_histogram!(
+        "api_checker_get_blob_sidecars_latency_milliseconds",
+        "Median latency of API responses for /eth/v1/beacon/blob_sidecars in millis",
+        HTTP_RESPONSE_TIME_CUSTOM_BUCKETS.to_vec(),
+    ).unwrap();
+    pub static ref GET_BLOCK_ATTESTATIONS_LATENCY_MILLISECONDS: Histogram = register_histogram!(
+        "api_checker_get_block_attestations_latency_milliseconds",
+        "Median latency of API responses for /eth/v1/beacon/blocks/attestations in millis",
+        HTTP_RESPONSE_TIME_CUSTOM_BUCKETS.to_vec(),
+    ).unwrap();
+    pub static ref GET_DEBUG_STATE_LATENCY_MILLISECONDS: Histogram = register_histogram!(
+        "api_checker_get_debug_state_latency_milliseconds",
+        "Median latency of API responses for /eth/v2/debug/beacon/states in millis",
+        HTTP_RESPONSE_TIME_CUSTOM_BUCKETS.to_vec(),
+    ).unwrap();
+    pub static ref GET_SSZ_LATENCY_MILLISECONDS: HistogramVec = register_histogram_vec!(
+        "api_checker_get_ssz_latency_milliseconds",
+        "Median latency of SSZ-encoded API responses by object in millis",
+        &["object"],
+        HTTP_RESPONSE_TIME_CUSTOM_BUCKETS.to_vec(),
+    ).unwrap();
+    pub static ref GET_OPENAPI_LATENCY_MILLISECONDS: HistogramVec = register_histogram_vec!(
+        "api_checker_get_openapi_latency_milliseconds",
+        "Latency of API responses to requests synthesized from the OpenAPI spec by path template in millis",
+        &["route"],
+        HTTP_RESPONSE_TIME_CUSTOM_BUCKETS.to_vec(),
+    ).unwrap();
+    pub static ref GET_POOL_LATENCY_MILLISECONDS: HistogramVec = register_histogram_vec!(
+        "api_checker_get_pool_latency_milliseconds",
+        "Median latency of API responses for /eth/v1/beacon/pool by pool in millis",
+        &["pool"],
+        HTTP_RESPONSE_TIME_CUSTOM_BUCKETS.to_vec(),
+    ).unwrap();
+
+    // Operation pool metrics.
+    pub static ref POOL_SIZE: IntGaugeVec = register_int_gauge_vec!(
+        "api_checker_pool_size",
+        "Number of operations in a pool by pool and endpoint",
+        &["pool", "endpoint"],
+    )
+    .unwrap();
+    pub static ref POOL_OVERLAP_RATIO: GaugeVec = register_gauge_vec!(
+        "api_checker_pool_overlap_ratio",
+        "Operations held by every endpoint over operations held by any endpoint by pool",
+        &["pool"],
+    )
+    .unwrap();
+    pub static ref POOL_MISSING: IntGaugeVec = register_int_gauge_vec!(
+        "api_checker_pool_missing",
+        "Operations seen by other endpoints but missing from an endpoint's pool",
+        &["pool", "endpoint"],
+    )
+    .unwrap();
+
+    // Node status metrics.
+    pub static ref NODE_HEALTH_STATUS: IntGaugeVec = register_int_gauge_vec!(
+        "api_checker_node_health_status",
+        "HTTP status code of /eth/v1/node/health by endpoint, 0 if unreachable",
+        &["endpoint"],
+    )
+    .unwrap();
+    pub static ref NODE_HEAD_SLOT: IntGaugeVec = register_int_gauge_vec!(
+        "api_checker_node_head_slot",
+        "Head slot reported by /eth/v1/node/syncing by endpoint",
+        &["endpoint"],
+    )
+    .unwrap();
+    pub static ref NODE_SYNC_DISTANCE: IntGaugeVec = register_int_gauge_vec!(
+        "api_checker_node_sync_distance",
+        "Sync distance reported by /eth/v1/node/syncing by endpoint",
+        &["endpoint"],
+    )
+    .unwrap();
+    pub static ref NODE_IS_SYNCING: IntGaugeVec = register_int_gauge_vec!(
+        "api_checker_node_is_syncing",
+        "Whether an endpoint reports itself as syncing",
+        &["endpoint"],
+    )
+    .unwrap();
+    pub static ref NODE_IS_OPTIMISTIC: IntGaugeVec = register_int_gauge_vec!(
+        "api_checker_node_is_optimistic",
+        "Whether an endpoint reports its head as optimistic",
+        &["endpoint"],
+    )
+    .unwrap();
+    pub static ref NODE_EL_OFFLINE: IntGaugeVec = register_int_gauge_vec!(
+        "api_checker_node_el_offline",
+        "Whether an endpoint reports its execution layer as offline",
+        &["endpoint"],
+    )
+    .unwrap();
+    pub static ref NODE_PEER_COUNT: IntGaugeVec = register_int_gauge_vec!(
+        "api_checker_node_peer_count",
+        "Peers reported by /eth/v1/node/peer_count by endpoint and connection state",
+        &["endpoint", "state"],
+    )
+    .unwrap();
+    pub static ref NODE_INFO: IntGaugeVec = register_int_gauge_vec!(
+        "api_checker_node_info",
+        "Identity reported by /eth/v1/node/identity by endpoint",
+        &["endpoint", "peer_id"],
+    )
+    .unwrap();
+
+    // Head monitor metrics.
+    pub static ref HEAD_SLOT_LAG: IntGaugeVec = register_int_gauge_vec!(
+        "api_checker_head_slot_lag",
+        "Slots an endpoint's head lags behind the highest head seen across endpoints",
+        &["endpoint"],
+    )
+    .unwrap();
+    pub static ref HEAD_REORGS_TOTAL: IntCounterVec = register_int_counter_vec!(
+        "api_checker_head_reorgs_total",
+        "Changes of the head root at an already seen slot by endpoint",
+        &["endpoint"],
+    )
+    .unwrap();
+
+    // Event stream metrics.
+    pub static ref EVENTS_RECEIVED_TOTAL: IntCounterVec = register_int_counter_vec!(
+        "api_checker_events_received_total",
+        "Events received from /eth/v1/events by topic and endpoint",
+        &["topic", "endpoint"],
+    )
+    .unwrap();
+    pub static ref EVENTS_MISSING_TOTAL: IntCounterVec = register_int_counter_vec!(
+        "api_checker_events_missing_total",
+        "Events emitted by other endpoints but never by an endpoint by topic",
+        &["topic", "endpoint"],
+    )
+    .unwrap();
+    pub static ref EVENTS_LATE_TOTAL: IntCounterVec = register_int_counter_vec!(
+        "api_checker_events_late_total",
+        "Events an endpoint emitted late relative to the first endpoint by topic",
+        &["topic", "endpoint"],
+    )
+    .unwrap();
+    pub static ref EVENTS_CONTRADICTORY_TOTAL: IntCounterVec = register_int_counter_vec!(
+        "api_checker_events_contradictory_total",
+        "Events which contradict the same event from another endpoint by topic",
+        &["topic", "endpoint"],
+    )
+    .unwrap();
+    pub static ref EVENT_DELAY_MILLISECONDS: HistogramVec = register_histogram_vec!(
+        "api_checker_event_delay_milliseconds",
+        "Delay of an event relative to the first endpoint which emitted it in millis",
+        &["topic", "endpoint"],
+        HTTP_RESPONSE_TIME_CUSTOM_BUCKETS.to_vec(),
+    )
+    .unwrap();
+    pub static ref SLO_COMPLIANCE_RATIO: GaugeVec = register_gauge_vec!(
+        "api_checker_slo_compliance_ratio",
+        "Fraction of requests of a check within its latency objective by endpoint and window in seconds",
+        &["check", "endpoint", "window"],
+    )
+    .unwrap();
+    pub static ref SLO_BURN_RATE: GaugeVec = register_gauge_vec!(
+        "api_checker_slo_burn_rate",
+        "How many times faster than allowed a check burns its latency error budget by endpoint and window in seconds",
+        &["check", "endpoint", "window"],
+    )
+    .unwrap();
+    pub static ref SLO_BREACHED: IntGaugeVec = register_int_gauge_vec!(
+        "api_checker_slo_breached",
+        "Whether an endpoint currently breaches the latency objective of a check",
+        &["check", "endpoint"],
+    )
+    .unwrap();
+    pub static ref SLO_BREACHES_TOTAL: IntCounterVec = register_int_counter_vec!(
+        "api_checker_slo_breaches_total",
+        "Times an endpoint started breaching the latency objective of a check",
+        &["check", "endpoint"],
+    )
+    .unwrap();
+    pub static ref ALERTS_SENT_TOTAL: IntCounterVec = register_int_counter_vec!(
+        "api_checker_alerts_sent_total",
+        "Alerts delivered by sink",
+        &["sink"],
+    )
+    .unwrap();
+    pub static ref ALERT_DELIVERY_FAILURES_TOTAL: IntCounterVec = register_int_counter_vec!(
+        "api_checker_alert_delivery_failures_total",
+        "Failed deliveries of a batch of alerts by sink",
+        &["sink"],
+    )
+    .unwrap();
+    pub static ref INCIDENTS_OPEN: IntGaugeVec = register_int_gauge_vec!(
+        "api_checker_incidents_open",
+        "Open incidents by kind, check and endpoint",
+        &["kind", "check", "endpoint"],
+    )
+    .unwrap();
+    pub static ref INCIDENTS_OPENED_TOTAL: IntCounterVec = register_int_counter_vec!(
+        "api_checker_incidents_opened_total",
+        "Incidents opened by kind",
+        &["kind"],
+    )
+    .unwrap();
 }
 
 pub async fn handler() -> Result<String, StatusCode> {
@@ -71,3 +467,65 @@ pub async fn handler() -> Result<String, StatusCode> {
         Err(_) => Err(StatusCode::INTERNAL_SERVER_ERROR),
     }
 }
+
+/// Updates the metrics of a check, unless it runs because it was triggered
+/// through the control API. Triggered runs use whatever parameters and
+/// endpoints were asked for, so they must not show up in the metrics of
+/// the scheduled runs.
+pub fn update(f: impl FnOnce()) {
+    if !crate::control::is_triggered() {
+        f();
+    }
+}
+
+/// Removes every series of an endpoint which is no longer checked, so its
+/// gauges don't report stale values and its label sets don't accumulate.
+pub fn forget(endpoint: &str) {
+    remove_endpoint(&LIGHT_CLIENT_UNSUPPORTED_TOTAL, endpoint);
+    remove_endpoint(&BLOB_COMMITMENTS_INCONSISTENT_TOTAL, endpoint);
+    remove_endpoint(&ENCODINGS_INCONSISTENT_TOTAL, endpoint);
+    remove_endpoint(&SELF_INCONSISTENT_TOTAL, endpoint);
+    remove_endpoint(&METADATA_INCONSISTENT_TOTAL, endpoint);
+    remove_endpoint(&ERROR_PATH_NONCONFORMANT_TOTAL, endpoint);
+    remove_endpoint(&SCHEMA_VIOLATIONS_TOTAL, endpoint);
+    remove_endpoint(&OPENAPI_UNSUPPORTED_TOTAL, endpoint);
+    remove_endpoint(&POOL_SIZE, endpoint);
+    remove_endpoint(&POOL_MISSING, endpoint);
+    remove_endpoint(&NODE_HEALTH_STATUS, endpoint);
+    remove_endpoint(&NODE_HEAD_SLOT, endpoint);
+    remove_endpoint(&NODE_SYNC_DISTANCE, endpoint);
+    remove_endpoint(&NODE_IS_SYNCING, endpoint);
+    remove_endpoint(&NODE_IS_OPTIMISTIC, endpoint);
+    remove_endpoint(&NODE_EL_OFFLINE, endpoint);
+    remove_endpoint(&NODE_PEER_COUNT, endpoint);
+    remove_endpoint(&NODE_INFO, endpoint);
+    remove_endpoint(&HEAD_SLOT_LAG, endpoint);
+    remove_endpoint(&HEAD_REORGS_TOTAL, endpoint);
+    remove_endpoint(&EVENTS_RECEIVED_TOTAL, endpoint);
+    remove_endpoint(&EVENTS_MISSING_TOTAL, endpoint);
+    remove_endpoint(&EVENTS_LATE_TOTAL, endpoint);
+    remove_endpoint(&EVENTS_CONTRADICTORY_TOTAL, endpoint);
+    remove_endpoint(&EVENT_DELAY_MILLISECONDS, endpoint);
+    remove_endpoint(&SLO_COMPLIANCE_RATIO, endpoint);
+    remove_endpoint(&SLO_BURN_RATE, endpoint);
+    remove_endpoint(&SLO_BREACHED, endpoint);
+    remove_endpoint(&SLO_BREACHES_TOTAL, endpoint);
+    remove_endpoint(&INCIDENTS_OPEN, endpoint);
+}
+
+// Removes the series of a metric whose endpoint label is the endpoint,
+// whatever its other labels.
+fn remove_endpoint<T: MetricVecBuilder>(metric: &MetricVec<T>, endpoint: &str) {
+    for family in metric.collect() {
+        for m in family.get_metric() {
+            let labels: HashMap<&str, &str> = m
+                .get_label()
+                .iter()
+                .map(|l| (l.get_name(), l.get_value()))
+                .collect();
+            if labels.get("endpoint") == Some(&endpoint) {
+                let _ = metric.remove(&labels);
+            }
+        }
+    }
+}
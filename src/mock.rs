@@ -0,0 +1,607 @@
+use axum::{
+    extract::State,
+    http::{Method, StatusCode, Uri},
+    response::{IntoResponse, Response},
+    Json, Router,
+};
+use ethereum_consensus::phase0::{mainnet::SignedBeaconBlock, Validator};
+use lazy_static::lazy_static;
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use std::net::SocketAddr;
+use std::sync::{Arc, RwLock};
+use tokio::time::{sleep, Duration};
+use url::Url;
+
+lazy_static! {
+    /// The mismatch counters are global, so tests asserting on them
+    /// must not run concurrently.
+    pub static ref SERIAL: tokio::sync::Mutex<()> = tokio::sync::Mutex::new(());
+}
+
+const ROOT: &str = "0x0101010101010101010101010101010101010101010101010101010101010101";
+const STATE_ROOT: &str = "0x0202020202020202020202020202020202020202020202020202020202020202";
+const SIGNATURE: &str = "0xc00000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000";
+
+/// The behavior of a mock node, which tests change to make it diverge
+/// from the others.
+#[derive(Default)]
+struct Knobs {
+    /// The bodies served by path template, such as
+    /// `/eth/v1/beacon/states/{state_id}/root`.
+    fixtures: HashMap<String, Value>,
+    /// Status codes served instead of the fixture by path template.
+    failing_routes: HashMap<String, StatusCode>,
+    /// Status code served instead of the fixture for every route.
+    failing: Option<StatusCode>,
+    /// How long to wait before answering each request.
+    latency: Duration,
+    /// Whether to never answer requests at all.
+    hanging: bool,
+}
+
+/// An in-process beacon node serving fixture data for every route
+/// the checker requests, with knobs to inject divergent values,
+/// latency, errors and timeouts.
+pub struct MockNode {
+    pub url: Url,
+    knobs: Arc<RwLock<Knobs>>,
+}
+
+impl MockNode {
+    /// Starts a mock node serving the default fixtures on a random local port.
+    pub async fn start() -> MockNode {
+        MockNode::start_with(default_fixtures()).await
+    }
+
+    /// Starts a mock node serving the given bodies by path template.
+    pub async fn start_with(fixtures: HashMap<String, Value>) -> MockNode {
+        let knobs = Arc::new(RwLock::new(Knobs {
+            fixtures,
+            ..Default::default()
+        }));
+        let router = Router::new().fallback(handler).with_state(knobs.clone());
+        let addr: SocketAddr = "127.0.0.1:0".parse().unwrap();
+        let server = axum::Server::bind(&addr).serve(router.into_make_service());
+        let url = Url::parse(&format!("http://{}/", server.local_addr())).unwrap();
+        tokio::spawn(server);
+        MockNode { url, knobs }
+    }
+
+    /// Serves a body for a path template instead of its default fixture.
+    pub fn set_fixture(&self, template: &str, body: Value) {
+        self.knobs
+            .write()
+            .unwrap()
+            .fixtures
+            .insert(template.to_string(), body);
+    }
+
+    /// Answers requests to a path template with a status code instead of its fixture.
+    pub fn fail_route(&self, template: &str, status: StatusCode) {
+        self.knobs
+            .write()
+            .unwrap()
+            .failing_routes
+            .insert(template.to_string(), status);
+    }
+
+    /// Answers every request with a status code, or serves fixtures again if none.
+    pub fn fail(&self, status: Option<StatusCode>) {
+        self.knobs.write().unwrap().failing = status;
+    }
+
+    /// Delays every response by the given duration.
+    pub fn set_latency(&self, latency: Duration) {
+        self.knobs.write().unwrap().latency = latency;
+    }
+
+    /// Accepts requests without ever answering them.
+    pub fn hang(&self, hanging: bool) {
+        self.knobs.write().unwrap().hanging = hanging;
+    }
+}
+
+async fn handler(State(knobs): State<Arc<RwLock<Knobs>>>, method: Method, uri: Uri) -> Response {
+    let (latency, hanging) = {
+        let knobs = knobs.read().unwrap();
+        (knobs.latency, knobs.hanging)
+    };
+    if hanging {
+        futures::future::pending::<()>().await;
+    }
+    sleep(latency).await;
+
+    let knobs = knobs.read().unwrap();
+    let template = knobs
+        .fixtures
+        .keys()
+        .chain(knobs.failing_routes.keys())
+        .find(|t| matches_template(t, uri.path()))
+        .cloned();
+    let template = match template {
+        Some(template) => template,
+        None => return error(StatusCode::NOT_FOUND, &format!("{} {}", method, uri)),
+    };
+    if let Some(status) = knobs
+        .failing
+        .or(knobs.failing_routes.get(&template).copied())
+    {
+        return error(status, "injected failure");
+    }
+    let body = match knobs.fixtures.get(&template) {
+        Some(body) => body.clone(),
+        None => return error(StatusCode::NOT_FOUND, &template),
+    };
+    if body.is_null() {
+        return StatusCode::OK.into_response();
+    }
+    // Versioned routes also report their fork in a header.
+    match body["version"].as_str().map(|v| v.to_string()) {
+        Some(version) => (
+            [(crate::http::CONSENSUS_VERSION_HEADER, version)],
+            Json(body),
+        )
+            .into_response(),
+        None => Json(body).into_response(),
+    }
+}
+
+fn error(status: StatusCode, message: &str) -> Response {
+    let body = json!({ "code": status.as_u16(), "message": message });
+    (status, Json(body)).into_response()
+}
+
+// Whether a request path matches a path template, with every `{param}`
+// segment of the template matching any single segment.
+fn matches_template(template: &str, path: &str) -> bool {
+    let template: Vec<&str> = template.trim_matches('/').split('/').collect();
+    let path: Vec<&str> = path.trim_matches('/').split('/').collect();
+    template.len() == path.len()
+        && template
+            .iter()
+            .zip(path.iter())
+            .all(|(t, p)| t.starts_with('{') || t == p)
+}
+
+fn checkpoint(epoch: u64, root: &str) -> Value {
+    json!({ "epoch": epoch.to_string(), "root": root })
+}
+
+/// Finality checkpoints with the given finalized epoch and root, used by
+/// tests to make a node diverge.
+pub fn finality_checkpoints(finalized_epoch: u64, finalized_root: &str) -> Value {
+    json!({
+        "execution_optimistic": false,
+        "finalized": true,
+        "data": {
+            "previous_justified": checkpoint(finalized_epoch + 1, ROOT),
+            "current_justified": checkpoint(finalized_epoch + 2, ROOT),
+            "finalized": checkpoint(finalized_epoch, finalized_root),
+        },
+    })
+}
+
+/// A state root response, used by tests to make a node diverge.
+pub fn state_root(root: &str) -> Value {
+    json!({ "execution_optimistic": false, "finalized": true, "data": { "root": root } })
+}
+
+/// The bodies every mock node serves unless a test overrides them, keyed by
+/// the path templates of the beacon-APIs spec.
+pub fn default_fixtures() -> HashMap<String, Value> {
+    let empty = json!({ "execution_optimistic": false, "finalized": true, "data": [] });
+    let validators: Vec<Value> = (0..4)
+        .map(|i| {
+            json!({
+                "index": i.to_string(),
+                "balance": "32000000000",
+                "status": "active_ongoing",
+                "validator": serde_json::to_value(Validator::default()).unwrap(),
+            })
+        })
+        .collect();
+    let balances: Vec<Value> = (0..4)
+        .map(|i| json!({ "index": i.to_string(), "balance": "32000000000" }))
+        .collect();
+    let header = json!({
+        "message": {
+            "slot": "100",
+            "proposer_index": "1",
+            "parent_root": ROOT,
+            "state_root": STATE_ROOT,
+            "body_root": ROOT,
+        },
+        "signature": SIGNATURE,
+    });
+    let light_client = json!({ "version": "capella", "data": { "attested_header": header } });
+
+    let fixtures = vec![
+        (
+            "/eth/v1/beacon/states/{state_id}/root",
+            state_root(STATE_ROOT),
+        ),
+        (
+            "/eth/v1/beacon/states/{state_id}/finality_checkpoints",
+            finality_checkpoints(1, ROOT),
+        ),
+        (
+            "/eth/v1/beacon/states/{state_id}/validators",
+            json!({ "execution_optimistic": false, "finalized": true, "data": validators }),
+        ),
+        (
+            "/eth/v1/beacon/states/{state_id}/validator_balances",
+            json!({ "execution_optimistic": false, "finalized": true, "data": balances }),
+        ),
+        (
+            "/eth/v2/beacon/blocks/{block_id}",
+            json!({
+                "version": "phase0",
+                "execution_optimistic": false,
+                "finalized": true,
+                "data": serde_json::to_value(SignedBeaconBlock::default()).unwrap(),
+            }),
+        ),
+        (
+            "/eth/v1/beacon/blocks/{block_id}/root",
+            json!({ "execution_optimistic": false, "finalized": true, "data": { "root": ROOT } }),
+        ),
+        (
+            "/eth/v1/beacon/blocks/{block_id}/attestations",
+            empty.clone(),
+        ),
+        (
+            "/eth/v1/beacon/headers/{block_id}",
+            json!({
+                "execution_optimistic": false,
+                "finalized": true,
+                "data": { "root": ROOT, "canonical": true, "header": header },
+            }),
+        ),
+        (
+            "/eth/v1/validator/duties/proposer/{epoch}",
+            json!({ "dependent_root": ROOT, "execution_optimistic": false, "data": [] }),
+        ),
+        (
+            "/eth/v1/validator/duties/attester/{epoch}",
+            json!({ "dependent_root": ROOT, "execution_optimistic": false, "data": [] }),
+        ),
+        ("/eth/v1/validator/duties/sync/{epoch}", empty.clone()),
+        (
+            "/eth/v1/beacon/rewards/blocks/{block_id}",
+            json!({
+                "execution_optimistic": false,
+                "finalized": true,
+                "data": {
+                    "proposer_index": "1",
+                    "total": "100",
+                    "attestations": "60",
+                    "sync_aggregate": "40",
+                    "proposer_slashings": "0",
+                    "attester_slashings": "0",
+                },
+            }),
+        ),
+        (
+            "/eth/v1/beacon/rewards/attestations/{epoch}",
+            json!({
+                "execution_optimistic": false,
+                "finalized": true,
+                "data": { "ideal_rewards": [], "total_rewards": [] },
+            }),
+        ),
+        (
+            "/eth/v1/beacon/rewards/sync_committee/{block_id}",
+            empty.clone(),
+        ),
+        (
+            "/eth/v1/beacon/light_client/bootstrap/{block_root}",
+            light_client.clone(),
+        ),
+        ("/eth/v1/beacon/light_client/updates", json!([light_client])),
+        (
+            "/eth/v1/beacon/light_client/finality_update",
+            light_client.clone(),
+        ),
+        (
+            "/eth/v1/beacon/light_client/optimistic_update",
+            light_client,
+        ),
+        ("/eth/v1/beacon/blob_sidecars/{block_id}", empty.clone()),
+        ("/eth/v1/beacon/pool/attestations", empty.clone()),
+        ("/eth/v1/beacon/pool/attester_slashings", empty.clone()),
+        ("/eth/v1/beacon/pool/proposer_slashings", empty.clone()),
+        ("/eth/v1/beacon/pool/voluntary_exits", empty.clone()),
+        ("/eth/v1/beacon/pool/bls_to_execution_changes", empty),
+        ("/eth/v1/node/health", Value::Null),
+        (
+            "/eth/v1/node/syncing",
+            json!({
+                "data": {
+                    "head_slot": "100",
+                    "sync_distance": "0",
+                    "is_syncing": false,
+                    "is_optimistic": false,
+                    "el_offline": false,
+                },
+            }),
+        ),
+        (
+            "/eth/v1/node/peer_count",
+            json!({
+                "data": {
+                    "disconnected": "0",
+                    "connecting": "0",
+                    "connected": "50",
+                    "disconnecting": "0",
+                },
+            }),
+        ),
+        (
+            "/eth/v1/node/version",
+            json!({ "data": { "version": "Mock/v0.0.1" } }),
+        ),
+        (
+            "/eth/v1/node/identity",
+            json!({ "data": { "peer_id": "16Uiu2HAmock", "enr": "enr:-mock" } }),
+        ),
+    ];
+    fixtures
+        .into_iter()
+        .map(|(template, body)| (template.to_string(), body))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::api_checker::{force_boxed, ApiChecker, CheckerFn};
+    use crate::endpoints::*;
+    use prometheus::core::Collector;
+
+    const OTHER_ROOT: &str = "0x0303030303030303030303030303030303030303030303030303030303030303";
+
+    async fn start_nodes(n: usize) -> Vec<MockNode> {
+        let mut nodes = vec![];
+        for _ in 0..n {
+            nodes.push(MockNode::start().await);
+        }
+        nodes
+    }
+
+    fn urls(nodes: &[MockNode]) -> Vec<Url> {
+        nodes.iter().map(|n| n.url.clone()).collect()
+    }
+
+    // Building a checker sets the HTTP timeout every test shares, so tests
+    // which build one restore it when they end, even if they fail.
+    struct RestoreTimeout(Duration);
+
+    impl RestoreTimeout {
+        fn new() -> RestoreTimeout {
+            RestoreTimeout(crate::http::timeout())
+        }
+    }
+
+    impl Drop for RestoreTimeout {
+        fn drop(&mut self) {
+            crate::http::set_timeout(self.0);
+        }
+    }
+
+    fn default_pipeline() -> Vec<CheckerFn> {
+        vec![
+            force_boxed(check_validators),
+            force_boxed(check_balances),
+            force_boxed(check_block),
+            force_boxed(check_finality_checkpoints),
+            force_boxed(check_state_root),
+            force_boxed(check_proposer_duties),
+            force_boxed(check_attester_duties),
+            force_boxed(check_sync_committee_duties),
+            force_boxed(check_block_rewards),
+            force_boxed(check_attestation_rewards),
+            force_boxed(check_sync_committee_rewards),
+            force_boxed(check_light_client_bootstrap),
+            force_boxed(check_light_client_updates),
+            force_boxed(check_light_client_finality_update),
+            force_boxed(check_light_client_optimistic_update),
+            force_boxed(check_blob_sidecars),
+            force_boxed(check_block_attestations),
+            force_boxed(check_pool_attestations),
+            force_boxed(check_pool_attester_slashings),
+            force_boxed(check_pool_proposer_slashings),
+            force_boxed(check_pool_voluntary_exits),
+            force_boxed(check_pool_bls_to_execution_changes),
+            force_boxed(check_metadata),
+        ]
+    }
+
+    fn mismatches() -> Vec<u64> {
+        vec![
+            crate::metrics::VALIDATORS_NOT_EQUAL_TOTAL.get(),
+            crate::metrics::BALANCES_NOT_EQUAL_TOTAL.get(),
+            crate::metrics::BLOCK_NOT_EQUAL_TOTAL.get(),
+            crate::metrics::CHECKPOINT_NOT_EQUAL_TOTAL.get(),
+            crate::metrics::STATE_ROOT_NOT_EQUAL_TOTAL.get(),
+            crate::metrics::PROPOSER_DUTIES_NOT_EQUAL_TOTAL.get(),
+            crate::metrics::ATTESTER_DUTIES_NOT_EQUAL_TOTAL.get(),
+            crate::metrics::SYNC_COMMITTEE_DUTIES_NOT_EQUAL_TOTAL.get(),
+            crate::metrics::BLOCK_REWARDS_NOT_EQUAL_TOTAL.get(),
+            crate::metrics::ATTESTATION_REWARDS_NOT_EQUAL_TOTAL.get(),
+            crate::metrics::SYNC_COMMITTEE_REWARDS_NOT_EQUAL_TOTAL.get(),
+            total(&crate::metrics::REWARDS_FIELD_NOT_EQUAL_TOTAL),
+            total(&crate::metrics::LIGHT_CLIENT_NOT_EQUAL_TOTAL),
+            crate::metrics::BLOB_SIDECARS_NOT_EQUAL_TOTAL.get(),
+            total(&crate::metrics::BLOB_COMMITMENTS_INCONSISTENT_TOTAL),
+            crate::metrics::BLOCK_ATTESTATIONS_NOT_EQUAL_TOTAL.get(),
+            total(&crate::metrics::METADATA_NOT_EQUAL_TOTAL),
+            total(&crate::metrics::METADATA_INCONSISTENT_TOTAL),
+            crate::metrics::INCIDENTS_OPENED_TOTAL
+                .with_label_values(&["mismatch"])
+                .get(),
+        ]
+    }
+
+    // Sums a counter over all of its labels.
+    fn total(counter: &prometheus::IntCounterVec) -> u64 {
+        counter
+            .collect()
+            .iter()
+            .flat_map(|family| family.get_metric())
+            .map(|m| m.get_counter().get_value() as u64)
+            .sum()
+    }
+
+    #[test]
+    fn test_matches_template() {
+        let template = "/eth/v1/beacon/states/{state_id}/root";
+        assert!(matches_template(
+            template,
+            "/eth/v1/beacon/states/head/root"
+        ));
+        assert!(matches_template(template, "/eth/v1/beacon/states/123/root"));
+        assert!(!matches_template(template, "/eth/v1/beacon/states/head"));
+        assert!(!matches_template(
+            template,
+            "/eth/v1/beacon/blocks/head/root"
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_identical_nodes_agree() {
+        let _guard = SERIAL.lock().await;
+        let _timeout = RestoreTimeout::new();
+        // The mock nodes serve no SSZ or debug states, so the SSZ, encoding,
+        // debug state and computed root checks are not covered here.
+        let nodes = start_nodes(3).await;
+        let before = mismatches();
+        let checker = ApiChecker::new()
+            .endpoints(urls(&nodes))
+            .pipeline(default_pipeline())
+            .build();
+        checker.run_pipeline().await.unwrap();
+        assert_eq!(mismatches(), before);
+    }
+
+    #[tokio::test]
+    async fn test_divergent_checkpoints_are_reported() {
+        let _guard = SERIAL.lock().await;
+        let nodes = start_nodes(3).await;
+        nodes[2].set_fixture(
+            "/eth/v1/beacon/states/{state_id}/finality_checkpoints",
+            finality_checkpoints(1, OTHER_ROOT),
+        );
+        let before = crate::metrics::CHECKPOINT_NOT_EQUAL_TOTAL.get();
+        check_finality_checkpoints(urls(&nodes)).await.unwrap();
+        assert_eq!(crate::metrics::CHECKPOINT_NOT_EQUAL_TOTAL.get(), before + 1);
+    }
+
+    #[tokio::test]
+    async fn test_divergent_state_root_is_reported() {
+        let _guard = SERIAL.lock().await;
+        let nodes = start_nodes(2).await;
+        nodes[1].set_fixture(
+            "/eth/v1/beacon/states/{state_id}/root",
+            state_root(OTHER_ROOT),
+        );
+        let before = crate::metrics::STATE_ROOT_NOT_EQUAL_TOTAL.get();
+        check_state_root(urls(&nodes)).await.unwrap();
+        assert_eq!(crate::metrics::STATE_ROOT_NOT_EQUAL_TOTAL.get(), before + 1);
+    }
+
+    #[tokio::test]
+    async fn test_failing_node_is_not_a_mismatch() {
+        let _guard = SERIAL.lock().await;
+        let _timeout = RestoreTimeout::new();
+        let nodes = start_nodes(3).await;
+        nodes[0].fail(Some(StatusCode::INTERNAL_SERVER_ERROR));
+        nodes[1].fail_route(
+            "/eth/v1/beacon/states/{state_id}/root",
+            StatusCode::SERVICE_UNAVAILABLE,
+        );
+        let before = mismatches();
+        let checker = ApiChecker::new()
+            .endpoints(urls(&nodes))
+            .pipeline(default_pipeline())
+            .build();
+        checker.run_pipeline().await.unwrap();
+        assert_eq!(mismatches(), before);
+    }
+
+    #[tokio::test]
+    async fn test_slow_node_is_still_compared() {
+        let _guard = SERIAL.lock().await;
+        let nodes = start_nodes(2).await;
+        nodes[0].set_latency(Duration::from_millis(200));
+        nodes[0].set_fixture(
+            "/eth/v1/beacon/states/{state_id}/root",
+            state_root(OTHER_ROOT),
+        );
+        let before = crate::metrics::STATE_ROOT_NOT_EQUAL_TOTAL.get();
+        check_state_root(urls(&nodes)).await.unwrap();
+        assert_eq!(crate::metrics::STATE_ROOT_NOT_EQUAL_TOTAL.get(), before + 1);
+    }
+
+    #[tokio::test]
+    async fn test_hanging_node_times_out() {
+        let _guard = SERIAL.lock().await;
+        let _timeout = RestoreTimeout::new();
+        let nodes = start_nodes(3).await;
+        nodes[1].set_fixture(
+            "/eth/v1/beacon/states/{state_id}/root",
+            state_root(OTHER_ROOT),
+        );
+        nodes[2].hang(true);
+        let checker = ApiChecker::new()
+            .endpoints(urls(&nodes))
+            .pipeline(vec![force_boxed(check_state_root)])
+            .timeout(Duration::from_millis(500))
+            .build();
+        let before = crate::metrics::STATE_ROOT_NOT_EQUAL_TOTAL.get();
+        let res = tokio::time::timeout(Duration::from_secs(5), checker.run_pipeline()).await;
+        assert!(res.is_ok(), "pipeline did not give up on the hanging node");
+        // The responsive nodes were still compared with each other.
+        assert_eq!(crate::metrics::STATE_ROOT_NOT_EQUAL_TOTAL.get(), before + 1);
+    }
+
+    #[tokio::test]
+    async fn test_triggered_check_uses_params() {
+        use axum::http::{header::AUTHORIZATION, HeaderMap};
+
+        let _guard = SERIAL.lock().await;
+        let nodes = start_nodes(2).await;
+        nodes[1].set_fixture(
+            "/eth/v1/beacon/states/{state_id}/root",
+            state_root(OTHER_ROOT),
+        );
+        crate::control::configure(
+            Some("secret".to_string()),
+            Arc::new(RwLock::new(urls(&nodes))),
+            Duration::from_secs(5),
+        );
+        let req = || {
+            Json(
+                serde_json::from_value(json!({ "check": "state_root", "state_id": "1234" }))
+                    .unwrap(),
+            )
+        };
+        let mut headers = HeaderMap::new();
+        let err = crate::control::trigger(headers.clone(), req())
+            .await
+            .unwrap_err();
+        assert_eq!(err.0, StatusCode::UNAUTHORIZED);
+
+        headers.insert(AUTHORIZATION, "Bearer secret".parse().unwrap());
+        let before = crate::metrics::STATE_ROOT_NOT_EQUAL_TOTAL.get();
+        let Json(res) = crate::control::trigger(headers, req()).await.unwrap();
+        assert_eq!(res["runs"][0]["method"], "/eth/v1/beacon/states/1234/root");
+        assert_eq!(res["runs"][0]["mismatched"], true);
+        // Triggered runs leave the scheduled runs' counters and incidents alone.
+        assert_eq!(crate::metrics::STATE_ROOT_NOT_EQUAL_TOTAL.get(), before);
+        assert!(!crate::incidents::open()
+            .iter()
+            .any(|i| i.check == "/eth/v1/beacon/states/{id}/root"
+                && urls(&nodes).iter().any(|u| u.as_str() == i.endpoint)));
+    }
+}
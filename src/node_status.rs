@@ -0,0 +1,177 @@
+use crate::api_checker::SharedEndpoints;
+use crate::types::{NodeIdentity, NodeVersion, PeerCount, SyncingStatus};
+use lazy_static::lazy_static;
+use std::collections::HashMap;
+use std::sync::RwLock;
+use tokio::time::{interval, Duration};
+use tracing::{info, warn};
+use url::Url;
+
+/// The latest self-reported status of a beacon node.
+#[derive(Debug, Clone, Default)]
+pub struct NodeStatus {
+    /// The HTTP status code of /eth/v1/node/health, if it could be reached.
+    pub health: Option<u16>,
+    pub syncing: Option<SyncingStatus>,
+    pub peers: Option<PeerCount>,
+    pub identity: Option<NodeIdentity>,
+    pub version: Option<NodeVersion>,
+}
+
+impl NodeStatus {
+    /// Whether the node should not be expected to agree with its peers,
+    /// either because it says it is still syncing or because it answered
+    /// the health route with 206 Partial Content.
+    pub fn is_syncing(&self) -> bool {
+        if self.health == Some(206) {
+            return true;
+        }
+        self.syncing.as_ref().map(|s| s.is_syncing).unwrap_or(false)
+    }
+}
+
+lazy_static! {
+    static ref NODE_STATUSES: RwLock<HashMap<Url, NodeStatus>> = RwLock::new(HashMap::new());
+}
+
+/// Returns the last polled status of an endpoint.
+pub fn status(endpoint: &Url) -> Option<NodeStatus> {
+    NODE_STATUSES.read().unwrap().get(endpoint).cloned()
+}
+
+/// Drops the status of an endpoint which is no longer checked.
+pub fn forget(endpoint: &Url) {
+    NODE_STATUSES.write().unwrap().remove(endpoint);
+}
+
+/// Whether an endpoint last reported itself as syncing. Endpoints which
+/// have not been polled yet are assumed to be synced.
+pub fn is_syncing(endpoint: &Url) -> bool {
+    status(endpoint).map(|s| s.is_syncing()).unwrap_or(false)
+}
+
+/// A suffix to append to an endpoint in logs so mismatches involving a
+/// node which is catching up can be told apart from consensus issues.
+pub fn annotation(endpoint: &Url) -> &'static str {
+    if is_syncing(endpoint) {
+        " (syncing)"
+    } else {
+        ""
+    }
+}
+
+/// Polls the node routes of every endpoint on an interval, keeping their
+/// statuses available to the checks and exporting them as metrics.
+pub async fn run_node_status(endpoints: SharedEndpoints, every: Duration) {
+    info!("Starting node status poller");
+    let mut ticker = interval(every);
+    loop {
+        ticker.tick().await;
+        let polled = endpoints.read().unwrap().clone();
+        for u in polled.iter() {
+            let status = poll_node_status(u).await;
+            // The endpoint may have been removed while it was polled, and
+            // holding the lock keeps it from being removed while recording.
+            let current = endpoints.read().unwrap();
+            if !current.contains(u) {
+                continue;
+            }
+            export_metrics(u, &status);
+            match status.health {
+                Some(health) if health < 500 => crate::incidents::clean(
+                    crate::incidents::IncidentKind::EndpointDown,
+                    "node_health",
+                    u.as_str(),
+                ),
+                health => crate::incidents::record(
+                    crate::incidents::IncidentKind::EndpointDown,
+                    "node_health",
+                    u.as_str(),
+                    format!("/eth/v1/node/health returned {:?}", health),
+                ),
+            }
+            NODE_STATUSES.write().unwrap().insert(u.clone(), status);
+        }
+    }
+}
+
+async fn poll_node_status(u: &Url) -> NodeStatus {
+    let mut status = NodeStatus::default();
+    match crate::http::get_status(u, "eth/v1/node/health").await {
+        Ok(code) => status.health = Some(code.as_u16()),
+        Err(e) => warn!(
+            "Request method=/eth/v1/node/health endpoint={} failed {:?}",
+            u, e
+        ),
+    }
+    match crate::http::get(u, "eth/v1/node/syncing").await {
+        Ok(res) => status.syncing = Some(res),
+        Err(e) => warn!(
+            "Request method=/eth/v1/node/syncing endpoint={} failed {:?}",
+            u, e
+        ),
+    }
+    match crate::http::get(u, "eth/v1/node/peer_count").await {
+        Ok(res) => status.peers = Some(res),
+        Err(e) => warn!(
+            "Request method=/eth/v1/node/peer_count endpoint={} failed {:?}",
+            u, e
+        ),
+    }
+    match crate::http::get(u, "eth/v1/node/identity").await {
+        Ok(res) => status.identity = Some(res),
+        Err(e) => warn!(
+            "Request method=/eth/v1/node/identity endpoint={} failed {:?}",
+            u, e
+        ),
+    }
+    match crate::http::get(u, "eth/v1/node/version").await {
+        Ok(res) => status.version = Some(res),
+        Err(e) => warn!(
+            "Request method=/eth/v1/node/version endpoint={} failed {:?}",
+            u, e
+        ),
+    }
+    status
+}
+
+fn export_metrics(u: &Url, status: &NodeStatus) {
+    let endpoint = u.as_str();
+    crate::metrics::NODE_HEALTH_STATUS
+        .with_label_values(&[endpoint])
+        .set(status.health.map(|h| h as i64).unwrap_or(0));
+    if let Some(syncing) = &status.syncing {
+        crate::metrics::NODE_HEAD_SLOT
+            .with_label_values(&[endpoint])
+            .set(syncing.head_slot as i64);
+        crate::metrics::NODE_SYNC_DISTANCE
+            .with_label_values(&[endpoint])
+            .set(syncing.sync_distance as i64);
+        crate::metrics::NODE_IS_SYNCING
+            .with_label_values(&[endpoint])
+            .set(syncing.is_syncing as i64);
+        crate::metrics::NODE_IS_OPTIMISTIC
+            .with_label_values(&[endpoint])
+            .set(syncing.is_optimistic as i64);
+        crate::metrics::NODE_EL_OFFLINE
+            .with_label_values(&[endpoint])
+            .set(syncing.el_offline as i64);
+    }
+    if let Some(peers) = &status.peers {
+        for (state, count) in [
+            ("disconnected", peers.disconnected),
+            ("connecting", peers.connecting),
+            ("connected", peers.connected),
+            ("disconnecting", peers.disconnecting),
+        ] {
+            crate::metrics::NODE_PEER_COUNT
+                .with_label_values(&[endpoint, state])
+                .set(count as i64);
+        }
+    }
+    if let Some(identity) = &status.identity {
+        crate::metrics::NODE_INFO
+            .with_label_values(&[endpoint, &identity.peer_id])
+            .set(1);
+    }
+}
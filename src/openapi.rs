@@ -0,0 +1,584 @@
+use eyre::{eyre, Result};
+use lazy_static::lazy_static;
+use regex::Regex;
+use serde_json::Value;
+use std::collections::HashMap;
+use std::path::Path;
+use std::sync::{Arc, Mutex, RwLock};
+use tracing::{info, warn};
+use url::Url;
+
+/// How many violations of a single response to log before truncating.
+const MAX_LOGGED_VIOLATIONS: usize = 8;
+
+/// Operations with these tags are left out of automatic coverage, as their
+/// responses legitimately differ between nodes, such as each client's
+/// config or the duties of its validators, are streamed, or are too large
+/// to request every run and have dedicated checks.
+const SKIPPED_TAGS: &[&str] = &["Node", "Events", "Debug", "Config", "Validator"];
+
+/// Operations which answer relative to the head without taking an id, so
+/// nodes answering at slightly different times legitimately disagree.
+const SKIPPED_ROUTES: &[&str] = &[
+    "/eth/v1/beacon/headers",
+    "/eth/v1/beacon/deposit_snapshot",
+    "/eth/v1/beacon/light_client/finality_update",
+    "/eth/v1/beacon/light_client/optimistic_update",
+];
+
+/// Operations under these prefixes are left out of automatic coverage, as
+/// operation pools legitimately differ between nodes.
+const SKIPPED_PREFIXES: &[&str] = &["/eth/v1/beacon/pool/"];
+
+/// Optional query parameters which are filled in when synthesizing requests,
+/// as without them routes such as `validators` return the whole validator
+/// set from every node every run.
+const FILTER_PARAMS: &[&str] = &["id", "index"];
+
+/// Values for the parameters of spec operations, taken from the chain so
+/// every node should be able to answer the synthesized requests.
+#[derive(Debug, Clone)]
+pub struct ChainContext {
+    pub state_id: String,
+    pub block_id: String,
+    pub block_root: String,
+    pub slot: u64,
+    pub epoch: u64,
+    pub sync_committee_period: u64,
+    pub validator_indices: Vec<u64>,
+}
+
+impl ChainContext {
+    fn param(&self, name: &str) -> Option<String> {
+        let indices: Vec<String> = self
+            .validator_indices
+            .iter()
+            .map(|i| i.to_string())
+            .collect();
+        let value = match name {
+            "state_id" => self.state_id.clone(),
+            "block_id" => self.block_id.clone(),
+            "block_root" => self.block_root.clone(),
+            "slot" => self.slot.to_string(),
+            "epoch" => self.epoch.to_string(),
+            "validator_id" => indices.first()?.clone(),
+            "id" => indices.join(","),
+            "start_period" => self.sync_committee_period.to_string(),
+            "count" => "1".to_string(),
+            "committee_index" | "index" => "0".to_string(),
+            _ => return None,
+        };
+        Some(value)
+    }
+}
+
+/// A beacon-APIs OpenAPI document used to validate the raw JSON responses
+/// of every node, which catches non-conformant clients even when every
+/// node agrees with each other.
+///
+/// The document must be bundled, i.e. all `$ref`s must point into the
+/// document itself as in the spec's release artifacts.
+pub struct Spec {
+    doc: Value,
+    patterns: Mutex<HashMap<String, Option<Regex>>>,
+}
+
+lazy_static! {
+    static ref SPEC: RwLock<Option<Arc<Spec>>> = RwLock::new(None);
+}
+
+/// Loads the spec from a JSON or YAML file and enables validation of
+/// every response fetched through the http module.
+pub fn load(path: &Path) -> Result<()> {
+    let contents = std::fs::read_to_string(path)?;
+    let doc: Value = match path.extension().and_then(|e| e.to_str()) {
+        Some("yaml") | Some("yml") => serde_yaml::from_str(&contents)?,
+        _ => serde_json::from_str(&contents)?,
+    };
+    if !doc["paths"].is_object() {
+        return Err(eyre!("{} has no paths", path.display()));
+    }
+    let external = external_refs(&doc);
+    if external > 0 {
+        warn!(
+            "OpenAPI spec {} has {} external $refs which will not be validated, use a bundled spec",
+            path.display(),
+            external,
+        );
+    }
+    info!(
+        "Loaded OpenAPI spec {} with {} paths",
+        path.display(),
+        doc["paths"].as_object().map(|p| p.len()).unwrap_or(0),
+    );
+    *SPEC.write().unwrap() = Some(Arc::new(Spec {
+        doc,
+        patterns: Mutex::new(HashMap::new()),
+    }));
+    Ok(())
+}
+
+// Counts the `$ref`s which point outside of the document.
+fn external_refs(value: &Value) -> usize {
+    match value {
+        Value::Object(object) => object
+            .iter()
+            .map(|(key, v)| match (key.as_str(), v.as_str()) {
+                ("$ref", Some(r)) if !r.starts_with('#') => 1,
+                _ => external_refs(v),
+            })
+            .sum(),
+        Value::Array(array) => array.iter().map(external_refs).sum(),
+        _ => 0,
+    }
+}
+
+/// Returns the loaded spec, if any.
+pub fn spec() -> Option<Arc<Spec>> {
+    SPEC.read().unwrap().clone()
+}
+
+/// Validates a response from an endpoint against the loaded spec, if any,
+/// reporting every violation of the operation's response schema.
+pub fn check_response(endpoint: &Url, method: &str, path: &str, status: u16, body: &Value) {
+    let spec = match spec() {
+        Some(spec) => spec,
+        None => return,
+    };
+    let (template, violations) = match spec.validate_response(method, path, status, body) {
+        Some(res) => res,
+        None => return,
+    };
+    if violations.is_empty() {
+        return;
+    }
+    crate::metrics::update(|| {
+        crate::metrics::SCHEMA_VIOLATIONS_TOTAL
+            .with_label_values(&[&template, endpoint.as_str()])
+            .inc_by(violations.len() as u64)
+    });
+    warn!(
+        "SCHEMA VIOLATION: endpoint={} method={} /{} status={} violations={:?}{}",
+        endpoint,
+        method.to_uppercase(),
+        path,
+        status,
+        &violations[..violations.len().min(MAX_LOGGED_VIOLATIONS)],
+        if violations.len() > MAX_LOGGED_VIOLATIONS {
+            format!(" and {} more", violations.len() - MAX_LOGGED_VIOLATIONS)
+        } else {
+            String::new()
+        },
+    );
+}
+
+impl Spec {
+    /// Returns the path templates and operations of the spec for a method,
+    /// e.g. `get`.
+    pub fn operations<'a>(&'a self, method: &'a str) -> impl Iterator<Item = (&'a str, &'a Value)> {
+        self.doc["paths"]
+            .as_object()
+            .into_iter()
+            .flat_map(|paths| paths.iter())
+            .filter_map(move |(template, item)| item.get(method).map(|op| (template.as_str(), op)))
+    }
+
+    /// Synthesizes a concrete request path for every GET operation of the spec
+    /// whose required parameters can be filled from the chain context, along
+    /// with the filters which keep responses small, returning each along with
+    /// its path template.
+    pub fn synthesize_gets(&self, ctx: &ChainContext) -> Vec<(String, String)> {
+        self.operations("get")
+            .filter(|(template, op)| {
+                let skipped_tag = op["tags"]
+                    .as_array()
+                    .map(|tags| {
+                        tags.iter()
+                            .any(|t| SKIPPED_TAGS.contains(&t.as_str().unwrap_or_default()))
+                    })
+                    .unwrap_or(false);
+                !skipped_tag
+                    && !SKIPPED_ROUTES.contains(template)
+                    && !SKIPPED_PREFIXES.iter().any(|p| template.starts_with(p))
+            })
+            .filter_map(|(template, op)| {
+                let mut path = template.trim_start_matches('/').to_string();
+                let mut query = vec![];
+                let params = op["parameters"].as_array().cloned().unwrap_or_default();
+                for param in params.iter() {
+                    let param = self.resolve_refs(param)?;
+                    let name = param["name"].as_str()?;
+                    let required = param["required"].as_bool().unwrap_or(false);
+                    match param["in"].as_str() {
+                        Some("path") => {
+                            path = path.replace(&format!("{{{}}}", name), &ctx.param(name)?);
+                        }
+                        Some("query") if required => {
+                            query.push(format!("{}={}", name, ctx.param(name)?));
+                        }
+                        Some("query") if FILTER_PARAMS.contains(&name) => {
+                            if let Some(value) = ctx.param(name) {
+                                query.push(format!("{}={}", name, value));
+                            }
+                        }
+                        _ => {}
+                    }
+                }
+                if !query.is_empty() {
+                    path = format!("{}?{}", path, query.join("&"));
+                }
+                Some((template.to_string(), path))
+            })
+            .collect()
+    }
+
+    /// Finds the path template of the spec which a concrete request path
+    /// matches, preferring templates with the most literal segments.
+    pub fn find_template(&self, method: &str, path: &str) -> Option<(&str, &Value)> {
+        let path = path.split('?').next().unwrap_or_default();
+        let segments: Vec<&str> = path.trim_matches('/').split('/').collect();
+        self.operations(method)
+            .filter_map(|(template, op)| {
+                let parts: Vec<&str> = template.trim_matches('/').split('/').collect();
+                if parts.len() != segments.len() {
+                    return None;
+                }
+                let mut literals = 0;
+                for (part, segment) in parts.iter().zip(segments.iter()) {
+                    if part.starts_with('{') {
+                        continue;
+                    }
+                    if part != segment {
+                        return None;
+                    }
+                    literals += 1;
+                }
+                Some((literals, template, op))
+            })
+            .max_by_key(|(literals, _, _)| *literals)
+            .map(|(_, template, op)| (template, op))
+    }
+
+    /// Validates a response body against the schema the spec declares for the
+    /// operation and status, returning the matched path template along with
+    /// any violations. Returns None if the spec doesn't describe the response.
+    pub fn validate_response(
+        &self,
+        method: &str,
+        path: &str,
+        status: u16,
+        body: &Value,
+    ) -> Option<(String, Vec<String>)> {
+        let (template, op) = self.find_template(method, path)?;
+        let response = self.resolve_refs(&op["responses"][status.to_string()])?;
+        let schema = response["content"]["application/json"].get("schema")?;
+        let mut violations = vec![];
+        self.validate(schema, body, "$", &mut violations);
+        Some((template.to_string(), violations))
+    }
+
+    // Follows `$ref`s until reaching an inline object.
+    fn resolve_refs<'a>(&'a self, mut value: &'a Value) -> Option<&'a Value> {
+        while let Some(reference) = value.get("$ref").and_then(|r| r.as_str()) {
+            value = self.resolve(reference)?;
+        }
+        if value.is_null() {
+            return None;
+        }
+        Some(value)
+    }
+
+    // External references are not followed, so the schemas behind them
+    // are not enforced.
+    fn resolve(&self, reference: &str) -> Option<&Value> {
+        let pointer = reference.strip_prefix('#')?;
+        self.doc.pointer(pointer)
+    }
+
+    fn matches(&self, pattern: &str, s: &str) -> bool {
+        let mut patterns = self.patterns.lock().unwrap();
+        let regex = patterns
+            .entry(pattern.to_string())
+            .or_insert_with(|| Regex::new(pattern).ok());
+        // Patterns we can't compile are not held against the node.
+        regex.as_ref().map(|r| r.is_match(s)).unwrap_or(true)
+    }
+
+    /// Validates a value against the subset of JSON schema used by the
+    /// beacon-APIs spec, appending violations with the path they occurred at.
+    pub fn validate(&self, schema: &Value, value: &Value, at: &str, violations: &mut Vec<String>) {
+        let schema = match self.resolve_refs(schema) {
+            Some(schema) => schema,
+            None => return,
+        };
+        if value.is_null() && schema["nullable"].as_bool() == Some(true) {
+            return;
+        }
+        if let Some(all) = schema["allOf"].as_array() {
+            for s in all.iter() {
+                self.validate(s, value, at, violations);
+            }
+        }
+        // A value may match several branches of a oneOf. The spec's fork
+        // versioned schemas don't forbid additional properties, so a later
+        // fork's object matches the schemas of earlier forks as well, and
+        // requiring exactly one match would flag every conformant node.
+        for key in ["oneOf", "anyOf"] {
+            if let Some(options) = schema[key].as_array() {
+                let matched = options.iter().any(|s| {
+                    let mut v = vec![];
+                    self.validate(s, value, at, &mut v);
+                    v.is_empty()
+                });
+                if !matched {
+                    violations.push(format!("{}: matches none of {}", at, key));
+                }
+            }
+        }
+        if let Some(options) = schema["enum"].as_array() {
+            if !options.contains(value) {
+                violations.push(format!("{}: {} is not one of {:?}", at, value, options));
+            }
+        }
+        let kind = schema["type"].as_str().or_else(|| {
+            if schema.get("properties").is_some() {
+                Some("object")
+            } else {
+                None
+            }
+        });
+        match kind {
+            Some("object") => self.validate_object(schema, value, at, violations),
+            Some("array") => self.validate_array(schema, value, at, violations),
+            Some("string") => self.validate_string(schema, value, at, violations),
+            Some("boolean") if !value.is_boolean() => {
+                violations.push(format!("{}: expected boolean, got {}", at, value));
+            }
+            Some("integer") if !(value.is_i64() || value.is_u64()) => {
+                violations.push(format!("{}: expected integer, got {}", at, value));
+            }
+            Some("number") if !value.is_number() => {
+                violations.push(format!("{}: expected number, got {}", at, value));
+            }
+            _ => {}
+        }
+    }
+
+    fn validate_object(
+        &self,
+        schema: &Value,
+        value: &Value,
+        at: &str,
+        violations: &mut Vec<String>,
+    ) {
+        let object = match value.as_object() {
+            Some(object) => object,
+            None => {
+                violations.push(format!("{}: expected object, got {}", at, value));
+                return;
+            }
+        };
+        if let Some(required) = schema["required"].as_array() {
+            for field in required.iter().filter_map(|f| f.as_str()) {
+                if !object.contains_key(field) {
+                    violations.push(format!("{}: missing required field {}", at, field));
+                }
+            }
+        }
+        let properties = schema["properties"].as_object();
+        for (field, v) in object.iter() {
+            let at = format!("{}.{}", at, field);
+            match (
+                properties.and_then(|p| p.get(field)),
+                &schema["additionalProperties"],
+            ) {
+                (Some(s), _) => self.validate(s, v, &at, violations),
+                (None, Value::Bool(false)) => {
+                    violations.push(format!("{}: unexpected field", at));
+                }
+                (None, s) if s.is_object() => self.validate(s, v, &at, violations),
+                (None, _) => {}
+            }
+        }
+    }
+
+    fn validate_array(
+        &self,
+        schema: &Value,
+        value: &Value,
+        at: &str,
+        violations: &mut Vec<String>,
+    ) {
+        let array = match value.as_array() {
+            Some(array) => array,
+            None => {
+                violations.push(format!("{}: expected array, got {}", at, value));
+                return;
+            }
+        };
+        if let Some(min) = schema["minItems"].as_u64() {
+            if (array.len() as u64) < min {
+                violations.push(format!("{}: fewer than {} items", at, min));
+            }
+        }
+        if let Some(max) = schema["maxItems"].as_u64() {
+            if array.len() as u64 > max {
+                violations.push(format!("{}: more than {} items", at, max));
+            }
+        }
+        if let Some(items) = schema.get("items") {
+            for (i, v) in array.iter().enumerate() {
+                self.validate(items, v, &format!("{}[{}]", at, i), violations);
+            }
+        }
+    }
+
+    // Integers and hex values such as roots are encoded as strings in the
+    // beacon API, so their format and length are enforced by patterns.
+    fn validate_string(
+        &self,
+        schema: &Value,
+        value: &Value,
+        at: &str,
+        violations: &mut Vec<String>,
+    ) {
+        let s = match value.as_str() {
+            Some(s) => s,
+            None => {
+                violations.push(format!("{}: expected string, got {}", at, value));
+                return;
+            }
+        };
+        if let Some(pattern) = schema["pattern"].as_str() {
+            if !self.matches(pattern, s) {
+                violations.push(format!("{}: {:?} does not match {}", at, s, pattern));
+            }
+        }
+        if let Some(min) = schema["minLength"].as_u64() {
+            if (s.len() as u64) < min {
+                violations.push(format!("{}: shorter than {} characters", at, min));
+            }
+        }
+        if let Some(max) = schema["maxLength"].as_u64() {
+            if s.len() as u64 > max {
+                violations.push(format!("{}: longer than {} characters", at, max));
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn spec(doc: Value) -> Spec {
+        Spec {
+            doc,
+            patterns: Mutex::new(HashMap::new()),
+        }
+    }
+
+    fn violations(spec: &Spec, schema: Value, value: Value) -> Vec<String> {
+        let mut violations = vec![];
+        spec.validate(&schema, &value, "$", &mut violations);
+        violations
+    }
+
+    #[test]
+    fn test_resolves_refs() {
+        let spec = spec(json!({
+            "components": { "schemas": {
+                "Root": { "$ref": "#/components/schemas/Hex" },
+                "Hex": { "type": "string", "pattern": "^0x[a-f0-9]{64}$" },
+            }}
+        }));
+        let schema = json!({ "$ref": "#/components/schemas/Root" });
+        let root = format!("0x{}", "ab".repeat(32));
+        assert!(violations(&spec, schema.clone(), json!(root)).is_empty());
+        assert_eq!(violations(&spec, schema, json!("0x01")).len(), 1);
+        // Unresolvable references are not held against the node.
+        let dangling = json!({ "$ref": "#/components/schemas/Missing" });
+        assert!(violations(&spec, dangling, json!(1)).is_empty());
+    }
+
+    #[test]
+    fn test_combinators() {
+        let spec = spec(json!({}));
+        let all_of = json!({ "allOf": [
+            { "type": "object", "required": ["a"] },
+            { "type": "object", "required": ["b"] },
+        ]});
+        assert!(violations(&spec, all_of.clone(), json!({ "a": 1, "b": 2 })).is_empty());
+        assert_eq!(violations(&spec, all_of, json!({ "a": 1 })).len(), 1);
+
+        let branches = json!([{ "type": "string" }, { "type": "integer" }]);
+        for key in ["oneOf", "anyOf"] {
+            let mut schema = json!({});
+            schema[key] = branches.clone();
+            assert!(violations(&spec, schema.clone(), json!("1")).is_empty());
+            assert!(violations(&spec, schema.clone(), json!(1)).is_empty());
+            assert_eq!(violations(&spec, schema, json!(true)).len(), 1);
+        }
+        // Matching several branches of a oneOf is tolerated.
+        let overlapping = json!({ "oneOf": [
+            { "type": "object", "required": ["a"] },
+            { "type": "object", "required": ["a", "b"] },
+        ]});
+        assert!(violations(&spec, overlapping, json!({ "a": 1, "b": 2 })).is_empty());
+    }
+
+    #[test]
+    fn test_object_properties() {
+        let spec = spec(json!({}));
+        let schema = json!({
+            "type": "object",
+            "required": ["slot"],
+            "properties": { "slot": { "type": "string" } },
+        });
+        assert!(violations(&spec, schema.clone(), json!({ "slot": "1", "extra": 1 })).is_empty());
+        assert_eq!(
+            violations(&spec, schema.clone(), json!({ "extra": 1 })),
+            vec!["$: missing required field slot"]
+        );
+        assert_eq!(
+            violations(&spec, schema, json!({ "slot": 1 })),
+            vec!["$.slot: expected string, got 1"]
+        );
+
+        let closed = json!({
+            "type": "object",
+            "properties": { "slot": { "type": "string" } },
+            "additionalProperties": false,
+        });
+        assert_eq!(
+            violations(&spec, closed, json!({ "slot": "1", "extra": 1 })),
+            vec!["$.extra: unexpected field"]
+        );
+        let map = json!({ "type": "object", "additionalProperties": { "type": "string" } });
+        assert!(violations(&spec, map.clone(), json!({ "a": "1" })).is_empty());
+        assert_eq!(violations(&spec, map, json!({ "a": 1 })).len(), 1);
+    }
+
+    #[test]
+    fn test_find_template() {
+        let spec = spec(json!({ "paths": {
+            "/eth/v1/beacon/states/{state_id}/root": { "get": { "operationId": "root" } },
+            "/eth/v1/beacon/states/{state_id}/{field}": { "get": { "operationId": "field" } },
+            "/eth/v1/beacon/headers/{block_id}": { "get": { "operationId": "header" } },
+        }}));
+        let template = |path: &str| spec.find_template("get", path).map(|(t, _)| t);
+        assert_eq!(
+            template("/eth/v1/beacon/states/head/root?x=1"),
+            Some("/eth/v1/beacon/states/{state_id}/root")
+        );
+        assert_eq!(
+            template("eth/v1/beacon/states/head/fork"),
+            Some("/eth/v1/beacon/states/{state_id}/{field}")
+        );
+        assert_eq!(template("/eth/v1/beacon/states/head"), None);
+        assert!(spec
+            .find_template("post", "/eth/v1/beacon/headers/head")
+            .is_none());
+    }
+}
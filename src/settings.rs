@@ -0,0 +1,244 @@
+use crate::api_checker::{ApiChecker, CheckerFn};
+use eyre::{eyre, Result};
+use serde::Deserialize;
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+use tokio::signal::unix::{signal, SignalKind};
+use tokio::sync::watch;
+use tokio::time::{interval, Duration};
+use tracing::{info, warn};
+use url::Url;
+
+/// Settings of the checker which can be changed while it runs, read from a
+/// YAML or JSON file. Whatever the file leaves out is taken from the
+/// command line.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize)]
+pub struct Settings {
+    /// The beacon api endpoints to request.
+    #[serde(default)]
+    pub endpoints: Option<Vec<Url>>,
+    /// How often to run the checks, in millis.
+    #[serde(default)]
+    pub interval_millis: Option<u64>,
+    /// The checks to run, named like their functions without the `check_`
+    /// prefix, e.g. `validators`.
+    #[serde(default)]
+    pub checks: Option<Vec<String>>,
+}
+
+impl Settings {
+    /// Fills in whatever these settings leave out from the defaults.
+    pub fn or(&self, defaults: &Settings) -> Settings {
+        Settings {
+            endpoints: self
+                .endpoints
+                .clone()
+                .or_else(|| defaults.endpoints.clone()),
+            interval_millis: self.interval_millis.or(defaults.interval_millis),
+            checks: self.checks.clone().or_else(|| defaults.checks.clone()),
+        }
+    }
+
+    /// Applies what changed since the previous settings to a running
+    /// checker. Endpoints are added and removed one by one, so those added
+    /// or removed through the control API in the meantime stay that way.
+    pub fn apply(&self, previous: &Settings, checker: &mut ApiChecker) {
+        let endpoints = self.endpoints.clone().unwrap_or_default();
+        let previous_endpoints = previous.endpoints.clone().unwrap_or_default();
+        let shared = checker.shared_endpoints();
+        let mut current = shared.write().unwrap();
+        for u in previous_endpoints.iter().filter(|u| !endpoints.contains(u)) {
+            if current.contains(u) {
+                info!("Removing endpoint={}", u);
+                current.retain(|c| c != u);
+                crate::control::forget(u);
+            }
+        }
+        for u in endpoints.iter().filter(|u| !previous_endpoints.contains(u)) {
+            if !current.contains(u) {
+                info!("Adding endpoint={}", u);
+                current.push(u.clone());
+            }
+        }
+        drop(current);
+        if self.interval_millis != previous.interval_millis {
+            if let Some(millis) = self.interval_millis {
+                checker.run_every = Duration::from_millis(millis);
+            }
+        }
+        if self.checks != previous.checks {
+            checker.set_pipeline(self.pipeline());
+        }
+    }
+
+    /// The functions of the enabled checks, whose names were validated
+    /// when the settings were loaded.
+    pub fn pipeline(&self) -> Vec<CheckerFn> {
+        self.checks
+            .iter()
+            .flatten()
+            .filter_map(|c| crate::control::check(c))
+            .collect()
+    }
+}
+
+/// Reads settings from a file, rejecting unknown checks.
+pub fn load(path: &Path) -> Result<Settings> {
+    let settings: Settings = serde_yaml::from_str(&std::fs::read_to_string(path)?)?;
+    if settings.interval_millis == Some(0) {
+        return Err(eyre!("interval_millis must be positive"));
+    }
+    for c in settings.checks.iter().flatten() {
+        if crate::control::check(c).is_none() {
+            return Err(eyre!("unknown check {}", c));
+        }
+    }
+    Ok(settings)
+}
+
+/// Reloads the settings file whenever it changes or the process receives
+/// SIGHUP, publishing settings which differ from the previous ones. A file
+/// which fails to load is logged and the previous settings kept.
+pub async fn watch(path: PathBuf, poll_every: Duration, sender: watch::Sender<Settings>) {
+    info!("Watching settings file {}", path.display());
+    let mut hangups = match signal(SignalKind::hangup()) {
+        Ok(hangups) => Some(hangups),
+        Err(e) => {
+            warn!("Listening for SIGHUP failed {:?}", e);
+            None
+        }
+    };
+    let mut ticker = interval(poll_every);
+    let mut modified = modified_at(&path);
+    loop {
+        tokio::select! {
+            _ = ticker.tick() => {
+                let m = modified_at(&path);
+                if m == modified {
+                    continue;
+                }
+                modified = m;
+            }
+            Some(()) = async { hangups.as_mut()?.recv().await } => {
+                info!("Received SIGHUP, reloading settings");
+            }
+        }
+        let settings = match load(&path) {
+            Ok(settings) => settings,
+            Err(e) => {
+                warn!("Reloading settings from {} failed {:?}", path.display(), e);
+                continue;
+            }
+        };
+        sender.send_if_modified(|current| {
+            if *current == settings {
+                return false;
+            }
+            info!("Reloaded settings from {}", path.display());
+            *current = settings;
+            true
+        });
+    }
+}
+
+fn modified_at(path: &Path) -> Option<SystemTime> {
+    std::fs::metadata(path).and_then(|m| m.modified()).ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn url(s: &str) -> Url {
+        Url::parse(s).unwrap()
+    }
+
+    fn write_settings(name: &str, contents: &str) -> PathBuf {
+        let path = std::env::temp_dir().join(format!(
+            "beacon-api-checker-{}-{}.yaml",
+            name,
+            std::process::id()
+        ));
+        std::fs::write(&path, contents).unwrap();
+        path
+    }
+
+    #[test]
+    fn test_load() {
+        let path = write_settings(
+            "load",
+            "endpoints: [\"http://a:5052/\"]\nchecks: [validators, state_root]\n",
+        );
+        let settings = load(&path).unwrap();
+        assert_eq!(settings.endpoints, Some(vec![url("http://a:5052/")]));
+        assert_eq!(settings.interval_millis, None);
+        assert_eq!(settings.pipeline().len(), 2);
+
+        std::fs::write(&path, "checks: [validators, nonexistent]\n").unwrap();
+        assert!(load(&path).is_err());
+        std::fs::write(&path, "interval_millis: 0\n").unwrap();
+        assert!(load(&path).is_err());
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn test_or() {
+        let defaults = Settings {
+            endpoints: Some(vec![url("http://a:5052/")]),
+            interval_millis: Some(250),
+            checks: Some(vec!["validators".to_string()]),
+        };
+        let file = Settings {
+            interval_millis: Some(1000),
+            ..Default::default()
+        };
+        let merged = file.or(&defaults);
+        assert_eq!(merged.endpoints, defaults.endpoints);
+        assert_eq!(merged.interval_millis, Some(1000));
+        assert_eq!(merged.checks, defaults.checks);
+    }
+
+    #[test]
+    fn test_apply_keeps_runtime_endpoints() {
+        let previous = Settings {
+            endpoints: Some(vec![url("http://a:5052/"), url("http://b:5052/")]),
+            interval_millis: Some(250),
+            checks: Some(vec!["validators".to_string()]),
+        };
+        let mut checker = ApiChecker::new().endpoints(previous.endpoints.clone().unwrap());
+        // An endpoint is added and another removed through the control API.
+        {
+            let shared = checker.shared_endpoints();
+            let mut current = shared.write().unwrap();
+            current.push(url("http://c:5052/"));
+            current.retain(|u| *u != url("http://b:5052/"));
+        }
+
+        // Changing only the interval leaves the endpoints alone.
+        let next = Settings {
+            interval_millis: Some(1000),
+            ..previous.clone()
+        };
+        next.apply(&previous, &mut checker);
+        assert_eq!(checker.run_every, Duration::from_millis(1000));
+        assert_eq!(
+            *checker.shared_endpoints().read().unwrap(),
+            vec![url("http://a:5052/"), url("http://c:5052/")]
+        );
+
+        // Endpoints are added and removed as the file changes them.
+        let last = Settings {
+            endpoints: Some(vec![url("http://b:5052/"), url("http://d:5052/")]),
+            ..next.clone()
+        };
+        last.apply(&next, &mut checker);
+        assert_eq!(
+            *checker.shared_endpoints().read().unwrap(),
+            vec![
+                url("http://c:5052/"),
+                url("http://b:5052/"),
+                url("http://d:5052/")
+            ]
+        );
+    }
+}
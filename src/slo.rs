@@ -0,0 +1,281 @@
+use eyre::{eyre, Result};
+use lazy_static::lazy_static;
+use serde::Deserialize;
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::path::Path;
+use std::sync::{Mutex, RwLock};
+use tokio::time::{interval, Duration, Instant};
+use tracing::{info, warn};
+use url::Url;
+
+/// A latency objective of a check, such as 99% of `get_validators`
+/// requests answering within 500ms.
+#[derive(Debug, Clone, Deserialize)]
+pub struct Objective {
+    /// The check the objective applies to, named like its latency metric
+    /// without the `api_checker_` prefix, e.g. `get_validators`.
+    pub check: String,
+    /// The endpoint the objective applies to, overriding objectives
+    /// without one, which apply to every endpoint.
+    #[serde(default)]
+    pub endpoint: Option<Url>,
+    /// The fraction of requests which must answer within the threshold.
+    pub percentile: f64,
+    pub threshold_millis: u64,
+}
+
+/// Objectives read from a YAML or JSON file.
+#[derive(Debug, Clone, Deserialize)]
+pub struct SloConfig {
+    /// The rolling windows compliance is computed over, in seconds. An
+    /// endpoint breaches an objective once it burns its error budget too
+    /// fast in every window, so short spikes alone don't alert.
+    #[serde(default = "default_windows")]
+    pub windows_secs: Vec<u64>,
+    /// How many requests a window needs before it is judged.
+    #[serde(default = "default_min_samples")]
+    pub min_samples: usize,
+    pub objectives: Vec<Objective>,
+}
+
+fn default_windows() -> Vec<u64> {
+    vec![300, 3600]
+}
+
+fn default_min_samples() -> usize {
+    10
+}
+
+/// The latencies of a check against an endpoint within the longest window,
+/// with failed requests as `Duration::MAX`.
+type Samples = VecDeque<(Instant, Duration)>;
+
+lazy_static! {
+    static ref CONFIG: RwLock<Option<SloConfig>> = RwLock::new(None);
+    static ref SAMPLES: Mutex<HashMap<(String, Url), Samples>> = Mutex::new(HashMap::new());
+    static ref BREACHED: Mutex<HashSet<(String, Url)>> = Mutex::new(HashSet::new());
+}
+
+/// Loads the objectives to track from a file.
+pub fn load(path: &Path) -> Result<()> {
+    let config: SloConfig = serde_yaml::from_str(&std::fs::read_to_string(path)?)?;
+    for o in config.objectives.iter() {
+        if !(0.0..1.0).contains(&o.percentile) {
+            return Err(eyre!(
+                "percentile of {} objective must be within [0, 1), got {}",
+                o.check,
+                o.percentile
+            ));
+        }
+    }
+    if config.windows_secs.is_empty() {
+        return Err(eyre!("at least one SLO window is required"));
+    }
+    info!(
+        "Loaded {} latency objectives from {}",
+        config.objectives.len(),
+        path.display()
+    );
+    *CONFIG.write().unwrap() = Some(config);
+    Ok(())
+}
+
+// Returns the objective of a check for an endpoint, preferring one
+// specific to the endpoint.
+fn objective(config: &SloConfig, check: &str, endpoint: &Url) -> Option<Objective> {
+    let objectives = config.objectives.iter().filter(|o| o.check == check);
+    let mut fallback = None;
+    for o in objectives {
+        match &o.endpoint {
+            Some(u) if u == endpoint => return Some(o.clone()),
+            Some(_) => {}
+            None => fallback = Some(o.clone()),
+        }
+    }
+    fallback
+}
+
+/// Records the latency of a successful request of a check to an endpoint.
+pub fn observe(check: &str, method: &str, endpoint: &Url, latency: Duration) {
+    crate::history::observe(method, endpoint, latency);
+    record(check, endpoint, latency);
+}
+
+/// Records a request of a check to an endpoint which failed or timed out.
+/// It never answered within the threshold, so it counts against the
+/// objective like a request slower than any threshold.
+pub fn observe_failure(check: &str, endpoint: &Url) {
+    record(check, endpoint, Duration::MAX);
+}
+
+fn record(check: &str, endpoint: &Url, latency: Duration) {
+    if crate::control::is_triggered() {
+        return;
+    }
+    let config = CONFIG.read().unwrap();
+    let config = match config.as_ref() {
+        Some(config) => config,
+        None => return,
+    };
+    if objective(config, check, endpoint).is_none() {
+        return;
+    }
+    SAMPLES
+        .lock()
+        .unwrap()
+        .entry((check.to_string(), endpoint.clone()))
+        .or_default()
+        .push_back((Instant::now(), latency));
+}
+
+/// Drops the latencies of an endpoint which is no longer checked.
+pub fn forget(endpoint: &Url) {
+    SAMPLES.lock().unwrap().retain(|(_, u), _| u != endpoint);
+    BREACHED.lock().unwrap().retain(|(_, u)| u != endpoint);
+}
+
+/// Periodically computes compliance with every objective over its rolling
+/// windows, exporting burn rates and alerting on endpoints which breach.
+pub async fn run_slo_monitor(every: Duration) {
+    info!("Starting latency SLO monitor");
+    let mut ticker = interval(every);
+    loop {
+        ticker.tick().await;
+        evaluate();
+    }
+}
+
+fn evaluate() {
+    let config = match CONFIG.read().unwrap().clone() {
+        Some(config) => config,
+        None => return,
+    };
+    let longest = Duration::from_secs(config.windows_secs.iter().copied().max().unwrap_or(0));
+    let now = Instant::now();
+    let mut samples = SAMPLES.lock().unwrap();
+    for ((check, endpoint), samples) in samples.iter_mut() {
+        while matches!(samples.front(), Some((at, _)) if now.duration_since(*at) > longest) {
+            samples.pop_front();
+        }
+        let o = match objective(&config, check, endpoint) {
+            Some(o) => o,
+            None => continue,
+        };
+        let mut breached = true;
+        for secs in config.windows_secs.iter() {
+            let window = Duration::from_secs(*secs);
+            let stats = window_stats(samples, now, window, &o, config.min_samples);
+            let (compliance, burn_rate) = match stats {
+                Some(stats) => stats,
+                None => {
+                    breached = false;
+                    continue;
+                }
+            };
+            let window_label = secs.to_string();
+            let labels = [check.as_str(), endpoint.as_str(), window_label.as_str()];
+            crate::metrics::SLO_COMPLIANCE_RATIO
+                .with_label_values(&labels)
+                .set(compliance);
+            crate::metrics::SLO_BURN_RATE
+                .with_label_values(&labels)
+                .set(burn_rate);
+            breached &= burn_rate > 1.0;
+        }
+        transition(check, endpoint, &o, breached);
+    }
+}
+
+// Computes the fraction of requests in a window which met the objective's
+// threshold, and how many times faster than allowed the window burns the
+// error budget of the objective.
+fn window_stats(
+    samples: &Samples,
+    now: Instant,
+    window: Duration,
+    o: &Objective,
+    min_samples: usize,
+) -> Option<(f64, f64)> {
+    let threshold = Duration::from_millis(o.threshold_millis);
+    let recent = samples
+        .iter()
+        .filter(|(at, _)| now.duration_since(*at) <= window);
+    let (total, slow) = recent.fold((0, 0), |(total, slow), (_, latency)| {
+        (total + 1, slow + (*latency > threshold) as usize)
+    });
+    if total < min_samples {
+        return None;
+    }
+    let slow_ratio = slow as f64 / total as f64;
+    Some((1.0 - slow_ratio, slow_ratio / (1.0 - o.percentile)))
+}
+
+fn transition(check: &str, endpoint: &Url, o: &Objective, breached: bool) {
+    if breached {
+        let sample = format!(
+            "p{}<{}ms breached",
+            o.percentile * 100.0,
+            o.threshold_millis
+        );
+        crate::incidents::record(
+            crate::incidents::IncidentKind::SloBreach,
+            check,
+            endpoint.as_str(),
+            sample,
+        );
+    } else {
+        crate::incidents::clean(
+            crate::incidents::IncidentKind::SloBreach,
+            check,
+            endpoint.as_str(),
+        );
+    }
+    let key = (check.to_string(), endpoint.clone());
+    let mut breaches = BREACHED.lock().unwrap();
+    let was_breached = breaches.contains(&key);
+    crate::metrics::SLO_BREACHED
+        .with_label_values(&[check, endpoint.as_str()])
+        .set(breached as i64);
+    if breached && !was_breached {
+        breaches.insert(key);
+        crate::metrics::SLO_BREACHES_TOTAL
+            .with_label_values(&[check, endpoint.as_str()])
+            .inc();
+        warn!(
+            "SLO BREACHED: check={} endpoint={} objective=p{}<{}ms",
+            check,
+            endpoint,
+            o.percentile * 100.0,
+            o.threshold_millis,
+        );
+    } else if !breached && was_breached {
+        breaches.remove(&key);
+        info!("SLO recovered: check={} endpoint={}", check, endpoint);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_window_stats() {
+        let o = Objective {
+            check: "get_validators".to_string(),
+            endpoint: None,
+            percentile: 0.9,
+            threshold_millis: 500,
+        };
+        let now = Instant::now();
+        let mut samples = Samples::new();
+        for i in 0..10 {
+            let latency = if i < 8 { 100 } else { 900 };
+            samples.push_back((now, Duration::from_millis(latency)));
+        }
+        let (compliance, burn_rate) =
+            window_stats(&samples, now, Duration::from_secs(60), &o, 10).unwrap();
+        assert!((compliance - 0.8).abs() < 1e-9);
+        assert!((burn_rate - 2.0).abs() < 1e-9);
+        assert!(window_stats(&samples, now, Duration::from_secs(60), &o, 11).is_none());
+    }
+}
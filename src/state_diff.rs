@@ -0,0 +1,186 @@
+use crate::types::FieldDiff;
+use ethereum_consensus::{altair, bellatrix, capella, phase0, primitives::Root};
+use eyre::{eyre, Result};
+use ssz_rs::{Deserialize, Merkleized};
+
+/// How many differing elements of a list to report before truncating.
+const MAX_LIST_DIFFS: usize = 16;
+
+/// A beacon state downloaded from the debug API, decoded with the
+/// container type of the fork the node reported it at.
+pub enum VersionedState {
+    Phase0(Box<phase0::mainnet::BeaconState>),
+    Altair(Box<altair::mainnet::BeaconState>),
+    Bellatrix(Box<bellatrix::mainnet::BeaconState>),
+    Capella(Box<capella::mainnet::BeaconState>),
+}
+
+impl VersionedState {
+    /// Decodes a state, or returns None for forks we have no
+    /// ethereum-consensus containers for, such as Deneb.
+    pub fn decode(version: &str, ssz: &[u8]) -> Result<Option<Self>> {
+        let invalid = |e| eyre!("invalid {} state ssz: {:?}", version, e);
+        let state = match version {
+            "phase0" => Self::Phase0(Box::new(
+                phase0::mainnet::BeaconState::deserialize(ssz).map_err(invalid)?,
+            )),
+            "altair" => Self::Altair(Box::new(
+                altair::mainnet::BeaconState::deserialize(ssz).map_err(invalid)?,
+            )),
+            "bellatrix" => Self::Bellatrix(Box::new(
+                bellatrix::mainnet::BeaconState::deserialize(ssz).map_err(invalid)?,
+            )),
+            "capella" => Self::Capella(Box::new(
+                capella::mainnet::BeaconState::deserialize(ssz).map_err(invalid)?,
+            )),
+            _ => return Ok(None),
+        };
+        Ok(Some(state))
+    }
+
+    pub fn version(&self) -> &'static str {
+        match self {
+            Self::Phase0(_) => "phase0",
+            Self::Altair(_) => "altair",
+            Self::Bellatrix(_) => "bellatrix",
+            Self::Capella(_) => "capella",
+        }
+    }
+
+    pub fn slot(&self) -> u64 {
+        match self {
+            Self::Phase0(s) => s.slot,
+            Self::Altair(s) => s.slot,
+            Self::Bellatrix(s) => s.slot,
+            Self::Capella(s) => s.slot,
+        }
+    }
+
+    /// Computes the state root, i.e. the hash tree root of the state.
+    pub fn root(&mut self) -> Result<Root> {
+        let root = match self {
+            Self::Phase0(s) => s.hash_tree_root(),
+            Self::Altair(s) => s.hash_tree_root(),
+            Self::Bellatrix(s) => s.hash_tree_root(),
+            Self::Capella(s) => s.hash_tree_root(),
+        };
+        root.map_err(|e| eyre!("failed to merkleize state: {:?}", e))
+    }
+}
+
+// States are far too large to log, so only identify them.
+impl std::fmt::Debug for VersionedState {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("VersionedState")
+            .field("version", &self.version())
+            .field("slot", &self.slot())
+            .finish()
+    }
+}
+
+impl PartialEq for VersionedState {
+    fn eq(&self, other: &Self) -> bool {
+        self.field_diff(other).is_empty()
+    }
+}
+
+impl Eq for VersionedState {}
+
+// Reports the indices at which two lists differ, so a mismatch in e.g. the
+// validator registry points at the validators involved.
+fn diff_list<T: PartialEq>(name: &str, a: &[T], b: &[T]) -> Vec<String> {
+    if a.len() != b.len() {
+        return vec![format!("{}.len", name)];
+    }
+    let mut diffs: Vec<String> = a
+        .iter()
+        .zip(b.iter())
+        .enumerate()
+        .filter(|(_, (x, y))| x != y)
+        .map(|(i, _)| format!("{}[{}]", name, i))
+        .collect();
+    if diffs.len() > MAX_LIST_DIFFS {
+        let remaining = diffs.len() - MAX_LIST_DIFFS;
+        diffs.truncate(MAX_LIST_DIFFS);
+        diffs.push(format!("{}[..] and {} more", name, remaining));
+    }
+    diffs
+}
+
+macro_rules! diff_state {
+    ($a:expr, $b:expr, fields: [$($field:ident),*], lists: [$($list:ident),*]) => {{
+        let mut diffs: Vec<String> = vec![];
+        $(
+            if $a.$field != $b.$field {
+                diffs.push(stringify!($field).to_string());
+            }
+        )*
+        $(
+            diffs.extend(diff_list(stringify!($list), &$a.$list, &$b.$list));
+        )*
+        diffs
+    }};
+}
+
+impl FieldDiff for VersionedState {
+    fn field_diff(&self, other: &Self) -> Vec<String> {
+        match (self, other) {
+            (Self::Phase0(a), Self::Phase0(b)) => diff_state!(a, b,
+                fields: [
+                    genesis_time, genesis_validators_root, slot, fork, latest_block_header,
+                    eth1_data, eth1_deposit_index, justification_bits,
+                    previous_justified_checkpoint, current_justified_checkpoint,
+                    finalized_checkpoint
+                ],
+                lists: [
+                    block_roots, state_roots, historical_roots, eth1_data_votes, validators,
+                    balances, randao_mixes, slashings, previous_epoch_attestations,
+                    current_epoch_attestations
+                ]
+            ),
+            (Self::Altair(a), Self::Altair(b)) => diff_state!(a, b,
+                fields: [
+                    genesis_time, genesis_validators_root, slot, fork, latest_block_header,
+                    eth1_data, eth1_deposit_index, justification_bits,
+                    previous_justified_checkpoint, current_justified_checkpoint,
+                    finalized_checkpoint, current_sync_committee, next_sync_committee
+                ],
+                lists: [
+                    block_roots, state_roots, historical_roots, eth1_data_votes, validators,
+                    balances, randao_mixes, slashings, previous_epoch_participation,
+                    current_epoch_participation, inactivity_scores
+                ]
+            ),
+            (Self::Bellatrix(a), Self::Bellatrix(b)) => diff_state!(a, b,
+                fields: [
+                    genesis_time, genesis_validators_root, slot, fork, latest_block_header,
+                    eth1_data, eth1_deposit_index, justification_bits,
+                    previous_justified_checkpoint, current_justified_checkpoint,
+                    finalized_checkpoint, current_sync_committee, next_sync_committee,
+                    latest_execution_payload_header
+                ],
+                lists: [
+                    block_roots, state_roots, historical_roots, eth1_data_votes, validators,
+                    balances, randao_mixes, slashings, previous_epoch_participation,
+                    current_epoch_participation, inactivity_scores
+                ]
+            ),
+            (Self::Capella(a), Self::Capella(b)) => diff_state!(a, b,
+                fields: [
+                    genesis_time, genesis_validators_root, slot, fork, latest_block_header,
+                    eth1_data, eth1_deposit_index, justification_bits,
+                    previous_justified_checkpoint, current_justified_checkpoint,
+                    finalized_checkpoint, current_sync_committee, next_sync_committee,
+                    latest_execution_payload_header, next_withdrawal_index,
+                    next_withdrawal_validator_index
+                ],
+                lists: [
+                    block_roots, state_roots, historical_roots, eth1_data_votes, validators,
+                    balances, randao_mixes, slashings, previous_epoch_participation,
+                    current_epoch_participation, inactivity_scores, historical_summaries
+                ]
+            ),
+            _ => vec!["version".to_string()],
+        }
+    }
+}
@@ -1,5 +1,11 @@
+use ethereum_consensus::primitives::Root;
+use serde::{Deserialize, Deserializer};
+use std::fmt::Display;
+use std::str::FromStr;
+
 use beacon_api_client::{
-    BalanceSummary, BlockId, FinalityCheckpoints, StateId, ValidatorStatus, ValidatorSummary,
+    AttestationDuty, BalanceSummary, BlockId, FinalityCheckpoints, ProposerDuty, StateId,
+    SyncCommitteeDuty, ValidatorStatus, ValidatorSummary,
 };
 
 pub struct StateIdExt {
@@ -151,3 +157,348 @@ impl PartialEq for BalanceSummaryExt {
         self.inner.balance == other.inner.balance
     }
 }
+
+#[derive(Debug)]
+pub struct ProposerDutyExt {
+    pub inner: ProposerDuty,
+}
+
+impl Eq for ProposerDutyExt {}
+
+impl PartialEq for ProposerDutyExt {
+    fn eq(&self, other: &Self) -> bool {
+        if self.inner.public_key != other.inner.public_key {
+            return false;
+        }
+        if self.inner.validator_index != other.inner.validator_index {
+            return false;
+        }
+        self.inner.slot == other.inner.slot
+    }
+}
+
+#[derive(Debug)]
+pub struct AttestationDutyExt {
+    pub inner: AttestationDuty,
+}
+
+impl Eq for AttestationDutyExt {}
+
+impl PartialEq for AttestationDutyExt {
+    fn eq(&self, other: &Self) -> bool {
+        if self.inner.public_key != other.inner.public_key {
+            return false;
+        }
+        if self.inner.validator_index != other.inner.validator_index {
+            return false;
+        }
+        if self.inner.committee_index != other.inner.committee_index {
+            return false;
+        }
+        if self.inner.committee_length != other.inner.committee_length {
+            return false;
+        }
+        if self.inner.committees_at_slot != other.inner.committees_at_slot {
+            return false;
+        }
+        if self.inner.validator_committee_index != other.inner.validator_committee_index {
+            return false;
+        }
+        self.inner.slot == other.inner.slot
+    }
+}
+
+#[derive(Debug)]
+pub struct SyncCommitteeDutyExt {
+    pub inner: SyncCommitteeDuty,
+}
+
+impl Eq for SyncCommitteeDutyExt {}
+
+impl PartialEq for SyncCommitteeDutyExt {
+    fn eq(&self, other: &Self) -> bool {
+        if self.inner.public_key != other.inner.public_key {
+            return false;
+        }
+        if self.inner.validator_index != other.inner.validator_index {
+            return false;
+        }
+        self.inner.validator_sync_committee_indices == other.inner.validator_sync_committee_indices
+    }
+}
+
+/// Deserializes the string-encoded integers used throughout the beacon API.
+fn quoted<'de, D, T>(deserializer: D) -> Result<T, D::Error>
+where
+    D: Deserializer<'de>,
+    T: FromStr,
+    T::Err: Display,
+{
+    let s = String::deserialize(deserializer)?;
+    s.parse().map_err(serde::de::Error::custom)
+}
+
+fn quoted_opt<'de, D, T>(deserializer: D) -> Result<Option<T>, D::Error>
+where
+    D: Deserializer<'de>,
+    T: FromStr,
+    T::Err: Display,
+{
+    match Option::<String>::deserialize(deserializer)? {
+        Some(s) => s.parse().map(Some).map_err(serde::de::Error::custom),
+        None => Ok(None),
+    }
+}
+
+/// Reports the names of the fields which differ between two responses
+/// so mismatches can be attributed to a specific part of a response.
+pub trait FieldDiff {
+    fn field_diff(&self, other: &Self) -> Vec<String>;
+}
+
+impl<T: FieldDiff> FieldDiff for Vec<T> {
+    fn field_diff(&self, other: &Self) -> Vec<String> {
+        if self.len() != other.len() {
+            return vec!["len".to_string()];
+        }
+        self.iter()
+            .zip(other.iter())
+            .flat_map(|(a, b)| a.field_diff(b))
+            .collect()
+    }
+}
+
+macro_rules! diff_fields {
+    ($a:expr, $b:expr, $prefix:expr, $($field:ident),+) => {{
+        let mut diffs = vec![];
+        $(
+            if $a.$field != $b.$field {
+                diffs.push(format!("{}{}", $prefix, stringify!($field)));
+            }
+        )+
+        diffs
+    }};
+}
+
+#[derive(Deserialize, Debug, PartialEq, Eq)]
+pub struct BlockRewards {
+    #[serde(deserialize_with = "quoted")]
+    pub proposer_index: u64,
+    #[serde(deserialize_with = "quoted")]
+    pub total: u64,
+    #[serde(deserialize_with = "quoted")]
+    pub attestations: u64,
+    #[serde(deserialize_with = "quoted")]
+    pub sync_aggregate: u64,
+    #[serde(deserialize_with = "quoted")]
+    pub proposer_slashings: u64,
+    #[serde(deserialize_with = "quoted")]
+    pub attester_slashings: u64,
+}
+
+impl FieldDiff for BlockRewards {
+    fn field_diff(&self, other: &Self) -> Vec<String> {
+        diff_fields!(
+            self,
+            other,
+            "",
+            proposer_index,
+            total,
+            attestations,
+            sync_aggregate,
+            proposer_slashings,
+            attester_slashings
+        )
+    }
+}
+
+#[derive(Deserialize, Debug, PartialEq, Eq)]
+pub struct IdealAttestationRewards {
+    #[serde(deserialize_with = "quoted")]
+    pub effective_balance: u64,
+    #[serde(deserialize_with = "quoted")]
+    pub head: i64,
+    #[serde(deserialize_with = "quoted")]
+    pub target: i64,
+    #[serde(deserialize_with = "quoted")]
+    pub source: i64,
+    #[serde(default, deserialize_with = "quoted_opt")]
+    pub inclusion_delay: Option<u64>,
+    #[serde(deserialize_with = "quoted")]
+    pub inactivity: i64,
+}
+
+impl FieldDiff for IdealAttestationRewards {
+    fn field_diff(&self, other: &Self) -> Vec<String> {
+        let prefix = format!("ideal_rewards[{}].", self.effective_balance);
+        if self.effective_balance != other.effective_balance {
+            return vec![format!("{}effective_balance", prefix)];
+        }
+        diff_fields!(
+            self,
+            other,
+            prefix,
+            head,
+            target,
+            source,
+            inclusion_delay,
+            inactivity
+        )
+    }
+}
+
+#[derive(Deserialize, Debug, PartialEq, Eq)]
+pub struct TotalAttestationRewards {
+    #[serde(deserialize_with = "quoted")]
+    pub validator_index: u64,
+    #[serde(deserialize_with = "quoted")]
+    pub head: i64,
+    #[serde(deserialize_with = "quoted")]
+    pub target: i64,
+    #[serde(deserialize_with = "quoted")]
+    pub source: i64,
+    #[serde(default, deserialize_with = "quoted_opt")]
+    pub inclusion_delay: Option<u64>,
+    #[serde(deserialize_with = "quoted")]
+    pub inactivity: i64,
+}
+
+impl FieldDiff for TotalAttestationRewards {
+    fn field_diff(&self, other: &Self) -> Vec<String> {
+        let prefix = format!("total_rewards[{}].", self.validator_index);
+        if self.validator_index != other.validator_index {
+            return vec![format!("{}validator_index", prefix)];
+        }
+        diff_fields!(
+            self,
+            other,
+            prefix,
+            head,
+            target,
+            source,
+            inclusion_delay,
+            inactivity
+        )
+    }
+}
+
+#[derive(Deserialize, Debug, PartialEq, Eq)]
+pub struct AttestationRewards {
+    pub ideal_rewards: Vec<IdealAttestationRewards>,
+    pub total_rewards: Vec<TotalAttestationRewards>,
+}
+
+impl FieldDiff for AttestationRewards {
+    fn field_diff(&self, other: &Self) -> Vec<String> {
+        let mut diffs = vec![];
+        if self.ideal_rewards.len() != other.ideal_rewards.len() {
+            diffs.push("ideal_rewards.len".to_string());
+        } else {
+            diffs.extend(self.ideal_rewards.field_diff(&other.ideal_rewards));
+        }
+        if self.total_rewards.len() != other.total_rewards.len() {
+            diffs.push("total_rewards.len".to_string());
+        } else {
+            diffs.extend(self.total_rewards.field_diff(&other.total_rewards));
+        }
+        diffs
+    }
+}
+
+#[derive(Deserialize, Debug, PartialEq, Eq)]
+pub struct SyncCommitteeReward {
+    #[serde(deserialize_with = "quoted")]
+    pub validator_index: u64,
+    #[serde(deserialize_with = "quoted")]
+    pub reward: i64,
+}
+
+impl FieldDiff for SyncCommitteeReward {
+    fn field_diff(&self, other: &Self) -> Vec<String> {
+        let prefix = format!("[{}].", self.validator_index);
+        if self.validator_index != other.validator_index {
+            return vec![format!("{}validator_index", prefix)];
+        }
+        diff_fields!(self, other, prefix, reward)
+    }
+}
+
+#[derive(Deserialize, Debug, PartialEq, Eq)]
+pub struct BlobSidecar {
+    #[serde(deserialize_with = "quoted")]
+    pub index: u64,
+    pub blob: String,
+    pub kzg_commitment: String,
+    pub kzg_proof: String,
+}
+
+impl FieldDiff for BlobSidecar {
+    fn field_diff(&self, other: &Self) -> Vec<String> {
+        let prefix = format!("[{}].", self.index);
+        if self.index != other.index {
+            return vec![format!("{}index", prefix)];
+        }
+        diff_fields!(self, other, prefix, blob, kzg_commitment, kzg_proof)
+    }
+}
+
+#[derive(Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct SyncingStatus {
+    #[serde(deserialize_with = "quoted")]
+    pub head_slot: u64,
+    #[serde(deserialize_with = "quoted")]
+    pub sync_distance: u64,
+    pub is_syncing: bool,
+    #[serde(default)]
+    pub is_optimistic: bool,
+    #[serde(default)]
+    pub el_offline: bool,
+}
+
+#[derive(Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct PeerCount {
+    #[serde(deserialize_with = "quoted")]
+    pub disconnected: u64,
+    #[serde(deserialize_with = "quoted")]
+    pub connecting: u64,
+    #[serde(deserialize_with = "quoted")]
+    pub connected: u64,
+    #[serde(deserialize_with = "quoted")]
+    pub disconnecting: u64,
+}
+
+#[derive(Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct NodeIdentity {
+    pub peer_id: String,
+    pub enr: String,
+}
+
+#[derive(Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct NodeVersion {
+    pub version: String,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+pub struct BeaconHeaderMessage {
+    #[serde(deserialize_with = "quoted")]
+    pub slot: u64,
+    pub parent_root: String,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+pub struct SignedBeaconHeader {
+    pub message: BeaconHeaderMessage,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+pub struct BeaconHeader {
+    pub root: String,
+    pub header: SignedBeaconHeader,
+}
+
+/// The `data` of the routes which report a root, such as the block root
+/// and state root routes. Headers also carry their block root in `root`.
+#[derive(Deserialize, Debug)]
+pub struct RootData {
+    pub root: Root,
+}